@@ -1,26 +1,127 @@
-use js_sys::Array;
-use serde::Serialize;
+use js_sys::{Array, Function};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
 use wasm_bindgen::prelude::*;
 
+/// Stable discriminant for every error the crate raises, exposed to JS as
+/// the `code` of the thrown error object. Codes never change meaning.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualListErrorKind {
+    InvalidConfig = 1,
+    InvalidSize = 2,
+    IndexOutOfBounds = 3,
+    InvalidViewport = 4,
+    EmptyList = 5,
+    PositionError = 6,
+    InvalidRange = 7,
+    InvalidChunkIndex = 8,
+    InvalidSections = 9,
+    InvalidUpdate = 10,
+    ChunkCreationError = 11,
+    ChunkError = 12,
+    UpdateError = 13,
+    CumulativeUpdateError = 14,
+    Frozen = 15,
+    InvalidState = 16,
+    InvalidJson = 17,
+    InvalidKinds = 18,
+}
+
+impl VirtualListErrorKind {
+    fn name(self) -> &'static str {
+        match self {
+            VirtualListErrorKind::InvalidConfig => "InvalidConfig",
+            VirtualListErrorKind::InvalidSize => "InvalidSize",
+            VirtualListErrorKind::IndexOutOfBounds => "IndexOutOfBounds",
+            VirtualListErrorKind::InvalidViewport => "InvalidViewport",
+            VirtualListErrorKind::EmptyList => "EmptyList",
+            VirtualListErrorKind::PositionError => "PositionError",
+            VirtualListErrorKind::InvalidRange => "InvalidRange",
+            VirtualListErrorKind::InvalidChunkIndex => "InvalidChunkIndex",
+            VirtualListErrorKind::InvalidSections => "InvalidSections",
+            VirtualListErrorKind::InvalidUpdate => "InvalidUpdate",
+            VirtualListErrorKind::ChunkCreationError => "ChunkCreationError",
+            VirtualListErrorKind::ChunkError => "ChunkError",
+            VirtualListErrorKind::UpdateError => "UpdateError",
+            VirtualListErrorKind::CumulativeUpdateError => "CumulativeUpdateError",
+            VirtualListErrorKind::Frozen => "Frozen",
+            VirtualListErrorKind::InvalidState => "InvalidState",
+            VirtualListErrorKind::InvalidJson => "InvalidJson",
+            VirtualListErrorKind::InvalidKinds => "InvalidKinds",
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct JsError {
     kind: String,
+    code: u32,
     message: String,
 }
 
 impl JsError {
-    fn new(kind: &str, message: &str) -> Self {
+    fn new(kind: VirtualListErrorKind, message: &str) -> Self {
         JsError {
-            kind: kind.to_string(),
+            kind: kind.name().to_string(),
+            code: kind as u32,
             message: message.to_string(),
         }
     }
 }
 
-fn convert_error(kind: &str, message: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&JsError::new(kind, message)).unwrap()
+/// Error of every fallible method. JS receives `List` errors as a
+/// `{ kind, code, message }` object and `Callback` errors as whatever the
+/// chunk or item callback threw.
+#[derive(Debug)]
+pub enum VirtualListError {
+    List {
+        kind: VirtualListErrorKind,
+        message: String,
+    },
+    Callback(JsValue),
+}
+
+impl From<VirtualListError> for JsValue {
+    fn from(error: VirtualListError) -> JsValue {
+        match error {
+            VirtualListError::List { kind, message } => {
+                serde_wasm_bindgen::to_value(&JsError::new(kind, &message)).unwrap()
+            }
+            VirtualListError::Callback(value) => value,
+        }
+    }
+}
+
+impl From<JsValue> for VirtualListError {
+    fn from(value: JsValue) -> Self {
+        VirtualListError::Callback(value)
+    }
+}
+
+fn convert_error(kind: VirtualListErrorKind, message: &str) -> VirtualListError {
+    VirtualListError::List {
+        kind,
+        message: message.to_string(),
+    }
+}
+
+// Index of the first element in `0..len` for which `is_before` is false,
+// assuming `is_before` holds for a prefix of the range.
+fn partition_point_by(len: usize, mut is_before: impl FnMut(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if is_before(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
 }
 
 #[wasm_bindgen]
@@ -30,14 +131,69 @@ pub enum Orientation {
     Horizontal,
 }
 
+impl Orientation {
+    // Coordinate name for offsets along this axis
+    fn axis(self) -> &'static str {
+        match self {
+            Orientation::Vertical => "y",
+            Orientation::Horizontal => "x",
+        }
+    }
+
+    // Dimension name for item sizes along this axis
+    fn dimension(self) -> &'static str {
+        match self {
+            Orientation::Vertical => "height",
+            Orientation::Horizontal => "width",
+        }
+    }
+
+    // The perpendicular axis, along which items have their cross size
+    fn cross(self) -> Orientation {
+        match self {
+            Orientation::Vertical => Orientation::Horizontal,
+            Orientation::Horizontal => Orientation::Vertical,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum ItemAlignment {
+    Start,
+    Center,
+    End,
+    /// Keep the current scroll if the item is fully visible, otherwise align
+    /// it to the nearest viewport edge.
+    Auto,
+}
+
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct VirtualListConfig {
     buffer_size: usize,
     overscan_items: usize,
-    #[allow(dead_code)]
     update_batch_size: usize,
     max_loaded_chunks: Option<usize>,
+    use_dynamic_estimate: bool,
+    gap: f64,
+    padding_start: f64,
+    padding_end: f64,
+    min_item_size: f64,
+    reverse: bool,
+    invert_axis: bool,
+    content_offset: f64,
+    boundary_hysteresis: f64,
+    overscan_px: Option<f64>,
+    skip_zero_sized_start: bool,
+    run_length_chunks: bool,
+    position_epsilon: f64,
+}
+
+impl Default for VirtualListConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -49,6 +205,19 @@ impl VirtualListConfig {
             overscan_items: 3,
             update_batch_size: 10,
             max_loaded_chunks: Some(100),
+            use_dynamic_estimate: false,
+            gap: 0.0,
+            padding_start: 0.0,
+            padding_end: 0.0,
+            min_item_size: 0.0,
+            reverse: false,
+            invert_axis: false,
+            content_offset: 0.0,
+            boundary_hysteresis: 0.0,
+            overscan_px: None,
+            skip_zero_sized_start: false,
+            run_length_chunks: false,
+            position_epsilon: 0.0,
         }
     }
 
@@ -72,6 +241,38 @@ impl VirtualListConfig {
         self.overscan_items = items;
     }
 
+    /// Overscan in pixels instead of items: when set, replaces
+    /// `overscan_items` with as many items as it takes to cover this many
+    /// pixels beyond each edge of the viewport.
+    #[wasm_bindgen(getter)]
+    pub fn overscan_px(&self) -> Option<f64> {
+        self.overscan_px
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_overscan_px(&mut self, pixels: Option<f64>) {
+        self.overscan_px = pixels.map(|pixels| {
+            if pixels.is_nan() {
+                0.0
+            } else {
+                pixels.max(0.0)
+            }
+        });
+    }
+
+    /// Number of item updates `batch_update_sizes` applies per internal pass.
+    /// Each pass groups its updates by chunk and propagates the resulting size
+    /// differences before the next pass starts.
+    #[wasm_bindgen(getter)]
+    pub fn update_batch_size(&self) -> usize {
+        self.update_batch_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_update_batch_size(&mut self, size: usize) {
+        self.update_batch_size = size.max(1);
+    }
+
     #[wasm_bindgen(getter)]
     pub fn max_loaded_chunks(&self) -> Option<usize> {
         self.max_loaded_chunks
@@ -81,6 +282,194 @@ impl VirtualListConfig {
     pub fn set_max_loaded_chunks(&mut self, max: Option<usize>) {
         self.max_loaded_chunks = max;
     }
+
+    /// When set, unloaded chunks are estimated from the running mean of all
+    /// measured sizes instead of the constructor's `estimated_size`. The
+    /// layout picks up a new mean once it moves more than 1% from the one
+    /// last applied, so steady measuring doesn't re-estimate every chunk.
+    #[wasm_bindgen(getter)]
+    pub fn use_dynamic_estimate(&self) -> bool {
+        self.use_dynamic_estimate
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_use_dynamic_estimate(&mut self, enabled: bool) {
+        self.use_dynamic_estimate = enabled;
+    }
+
+    /// Space between consecutive items. It is part of the scroll extent but
+    /// never of an item's own size; positions inside a gap resolve to the
+    /// preceding item.
+    #[wasm_bindgen(getter)]
+    pub fn gap(&self) -> f64 {
+        self.gap
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_gap(&mut self, gap: f64) {
+        self.gap = if gap.is_nan() { 0.0 } else { gap.max(0.0) };
+    }
+
+    /// Space before the first item, e.g. for a header rendered above the list.
+    /// Item offsets are measured from the start of this padding.
+    #[wasm_bindgen(getter)]
+    pub fn padding_start(&self) -> f64 {
+        self.padding_start
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_padding_start(&mut self, padding: f64) {
+        self.padding_start = if padding.is_nan() {
+            0.0
+        } else {
+            padding.max(0.0)
+        };
+    }
+
+    /// Space after the last item, e.g. for a footer rendered below the list.
+    #[wasm_bindgen(getter)]
+    pub fn padding_end(&self) -> f64 {
+        self.padding_end
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_padding_end(&mut self, padding: f64) {
+        self.padding_end = if padding.is_nan() {
+            0.0
+        } else {
+            padding.max(0.0)
+        };
+    }
+
+    /// Floor applied to every measured size, so runs of collapsed items keep
+    /// a nonzero extent. Clamped sizes are what `total_size` and all offsets
+    /// see; estimates and hints are not affected.
+    #[wasm_bindgen(getter)]
+    pub fn min_item_size(&self) -> f64 {
+        self.min_item_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_min_item_size(&mut self, size: f64) {
+        self.min_item_size = if size.is_nan() { 0.0 } else { size.max(0.0) };
+    }
+
+    /// Bottom-anchored layout for chat and log views: item 0 sits at the end
+    /// of the scroll extent, so a scroll position of 0 shows the last items.
+    /// Scroll positions passed in are still measured from the top, while item
+    /// offsets are measured from the bottom.
+    #[wasm_bindgen(getter)]
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    /// For hosts whose scroll position grows toward the start of the list:
+    /// positions passed in and returned are measured from the end of the
+    /// scroll range. Independent of `reverse`; setting both cancels out.
+    #[wasm_bindgen(getter)]
+    pub fn invert_axis(&self) -> bool {
+        self.invert_axis
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_invert_axis(&mut self, invert: bool) {
+        self.invert_axis = invert;
+    }
+
+    /// Distance from the scroll container's origin to where the list begins,
+    /// e.g. content rendered above it inside the same container. Scroll
+    /// positions passed in and returned are container positions; item
+    /// offsets stay relative to the list.
+    #[wasm_bindgen(getter)]
+    pub fn content_offset(&self) -> f64 {
+        self.content_offset
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_content_offset(&mut self, offset: f64) {
+        self.content_offset = if offset.is_nan() {
+            0.0
+        } else {
+            offset.max(0.0)
+        };
+    }
+
+    /// Pixels the scroll position must move past an item boundary before
+    /// `get_visible_range` moves its range, so jitter around a boundary
+    /// doesn't flip the range back and forth. 0 disables it.
+    #[wasm_bindgen(getter)]
+    pub fn boundary_hysteresis(&self) -> f64 {
+        self.boundary_hysteresis
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_boundary_hysteresis(&mut self, pixels: f64) {
+        self.boundary_hysteresis = if pixels.is_nan() {
+            0.0
+        } else {
+            pixels.max(0.0)
+        };
+    }
+
+    /// When the item at the viewport's leading edge is zero-sized, advances
+    /// to the next item with a positive size, and pulls the trailing edge
+    /// back past zero-sized items the same way, before buffer and overscan
+    /// are added. Collapsed rows at the viewport edges then don't anchor the
+    /// range. At least one item is kept.
+    #[wasm_bindgen(getter)]
+    pub fn skip_zero_sized_start(&self) -> bool {
+        self.skip_zero_sized_start
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_skip_zero_sized_start(&mut self, skip: bool) {
+        self.skip_zero_sized_start = skip;
+    }
+
+    /// Stores each chunk's sizes as runs of equal sizes instead of one entry
+    /// per item. Saves memory when most items share a size, at the cost of
+    /// O(runs) size updates. Applies to chunks loaded after it is set.
+    #[wasm_bindgen(getter)]
+    pub fn run_length_chunks(&self) -> bool {
+        self.run_length_chunks
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_run_length_chunks(&mut self, enabled: bool) {
+        self.run_length_chunks = enabled;
+    }
+
+    /// Tolerance for locating items by position: a leading edge at most this
+    /// far past the position counts as reached. Offsets past ~1e8 computed
+    /// on the JS side can land a hair short of an edge that was accumulated
+    /// differently here, resolving to the previous item; a small epsilon
+    /// such as 1e-6 snaps them onto the boundary. 0 disables it.
+    #[wasm_bindgen(getter)]
+    pub fn position_epsilon(&self) -> f64 {
+        self.position_epsilon
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_position_epsilon(&mut self, epsilon: f64) {
+        self.position_epsilon = if epsilon.is_nan() {
+            0.0
+        } else {
+            epsilon.max(0.0)
+        };
+    }
+}
+
+impl VirtualListConfig {
+    // Whether scroll positions run against item offsets, from `reverse` and
+    // `invert_axis` flipping the mapping independently
+    fn flips_scroll(&self) -> bool {
+        self.reverse != self.invert_axis
+    }
 }
 
 #[wasm_bindgen]
@@ -90,6 +479,10 @@ pub struct VisibleRange {
     end: usize,
     start_offset: f64,
     end_offset: f64,
+    first_fully_visible: usize,
+    last_fully_visible: usize,
+    sections: Vec<u32>,
+    pinned: Vec<u32>,
 }
 
 #[wasm_bindgen]
@@ -104,441 +497,5501 @@ impl VisibleRange {
         self.end
     }
 
+    /// Number of items to render, `end - start`.
+    #[wasm_bindgen(getter)]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Leading edge of item `start`, measured from the list's origin, i.e.
+    /// where the first rendered item should be placed.
     #[wasm_bindgen(getter)]
     pub fn start_offset(&self) -> f64 {
         self.start_offset
     }
 
+    /// Trailing edge of item `end - 1`, the last rendered item.
     #[wasm_bindgen(getter)]
     pub fn end_offset(&self) -> f64 {
         self.end_offset
     }
-}
 
-#[derive(Clone)]
-struct Chunk {
-    sizes: Vec<f64>,
-    prefix_sums: Vec<f64>,
-    total_size: f64,
-}
+    /// First item lying entirely inside the viewport, ignoring buffer and
+    /// overscan. If no item fits, the item at the viewport's leading edge.
+    #[wasm_bindgen(getter)]
+    pub fn first_fully_visible(&self) -> usize {
+        self.first_fully_visible
+    }
 
-impl Chunk {
-    fn new(chunk_size: usize, estimated_size: f64) -> Result<Self, String> {
-        if estimated_size.is_nan() || estimated_size < 0.0 {
-            return Err(format!("Invalid size: {}", estimated_size));
-        }
-        let sizes = vec![estimated_size; chunk_size];
-        let mut prefix_sums = Vec::with_capacity(chunk_size + 1);
-        prefix_sums.push(0.0);
-        let mut cumulative = 0.0;
-        for &size in &sizes {
-            cumulative += size;
-            prefix_sums.push(cumulative);
-        }
-        Ok(Chunk {
-            sizes,
-            prefix_sums,
-            total_size: cumulative,
-        })
+    /// Last item lying entirely inside the viewport. If no item fits, the
+    /// same item as `first_fully_visible`.
+    #[wasm_bindgen(getter)]
+    pub fn last_fully_visible(&self) -> usize {
+        self.last_fully_visible
     }
 
-    fn update_size(&mut self, index: usize, new_size: f64) -> Result<f64, String> {
-        if index >= self.sizes.len() {
-            return Err(format!("Index {} out of bounds", index));
-        }
-        if new_size.is_nan() || new_size < 0.0 {
-            return Err(format!("Invalid size: {}", new_size));
-        }
-        let old_size = self.sizes[index];
-        let diff = new_size - old_size;
-        self.sizes[index] = new_size;
-        self.total_size += diff;
-        for i in index + 1..self.prefix_sums.len() {
-            self.prefix_sums[i] += diff;
-        }
-        Ok(diff)
+    /// Sections whose headers sit before one of the rendered items, in
+    /// order. Place them with `VirtualList::section_header_offset`.
+    #[wasm_bindgen(getter)]
+    pub fn sections(&self) -> Vec<u32> {
+        self.sections.clone()
     }
 
-    fn find_item_at_position(&self, position: f64) -> Result<(usize, f64), String> {
-        if position.is_nan() || position < 0.0 || position > self.total_size {
-            return Err(format!("Invalid position: {}", position));
-        }
-        let index = self
-            .prefix_sums
-            .binary_search_by(|&sum| sum.partial_cmp(&position).unwrap_or(cmp::Ordering::Greater))
-            .unwrap_or_else(|e| e - 1);
-        let offset = position - self.prefix_sums[index];
-        Ok((index, offset))
+    /// Pinned items, which render outside the scrolled flow regardless of
+    /// scroll position. Any of them inside `[start, end)` should be skipped
+    /// there.
+    #[wasm_bindgen(getter)]
+    pub fn pinned(&self) -> Vec<u32> {
+        self.pinned.clone()
     }
 }
 
 #[wasm_bindgen]
-pub struct VirtualList {
-    total_items: usize,
-    estimated_size: f64,
-    #[allow(dead_code)]
-    orientation: Orientation,
-    chunks: Vec<Option<Chunk>>,
-    chunk_size: usize,
-    cumulative_sizes: Vec<f64>,
-    total_size: f64,
-    config: VirtualListConfig,
-    access_counter: u64,
-    chunk_access: HashMap<usize, u64>,
+#[derive(Clone, Copy)]
+pub struct VisibleRangeDetailed {
+    visible_start: usize,
+    visible_end: usize,
+    render_start: usize,
+    render_end: usize,
+    render_start_offset: f64,
+    render_end_offset: f64,
 }
 
 #[wasm_bindgen]
-impl VirtualList {
-    #[wasm_bindgen(constructor)]
-    pub fn new(
-        total_items: usize,
-        chunk_size: usize,
-        estimated_size: f64,
-        orientation: Orientation,
-        config: VirtualListConfig,
-    ) -> Result<VirtualList, JsValue> {
-        if chunk_size == 0 {
-            return Err(convert_error(
-                "InvalidConfig",
-                "chunk_size must be positive",
-            ));
-        }
-        if estimated_size.is_nan() || estimated_size < 0.0 {
-            return Err(convert_error(
-                "InvalidSize",
-                &format!("Invalid estimated size: {}", estimated_size),
-            ));
-        }
+impl VisibleRangeDetailed {
+    /// First item on screen.
+    #[wasm_bindgen(getter)]
+    pub fn visible_start(&self) -> usize {
+        self.visible_start
+    }
 
-        let num_chunks = (total_items + chunk_size - 1) / chunk_size;
-        let mut cumulative_sizes = Vec::with_capacity(num_chunks);
-        let mut total_size = 0.0;
-        for i in 0..num_chunks {
-            let items_in_chunk = if i == num_chunks - 1 && total_items % chunk_size != 0 {
-                total_items % chunk_size
-            } else {
-                chunk_size
-            };
-            let chunk_total = estimated_size * items_in_chunk as f64;
-            total_size += chunk_total;
-            cumulative_sizes.push(total_size);
-        }
-        Ok(VirtualList {
-            total_items,
-            estimated_size,
-            orientation,
-            chunks: vec![None; num_chunks],
-            chunk_size,
-            cumulative_sizes,
-            total_size,
-            config,
-            access_counter: 0,
-            chunk_access: HashMap::new(),
-        })
+    /// One past the last item on screen.
+    #[wasm_bindgen(getter)]
+    pub fn visible_end(&self) -> usize {
+        self.visible_end
     }
 
-    fn get_or_create_chunk(&mut self, chunk_idx: usize) -> Result<&mut Chunk, JsValue> {
-        if chunk_idx >= self.chunks.len() {
-            return Err(convert_error(
-                "InvalidChunkIndex",
-                &format!("Chunk index {} out of bounds", chunk_idx),
-            ));
-        }
+    /// First item to render, including buffer and overscan.
+    #[wasm_bindgen(getter)]
+    pub fn render_start(&self) -> usize {
+        self.render_start
+    }
 
-        // Handle unloading before borrowing the chunk
-        if let Some(max) = self.config.max_loaded_chunks {
-            if self.chunk_access.len() >= max && !self.chunk_access.contains_key(&chunk_idx) {
-                if let Some((&lru_chunk, _)) =
-                    self.chunk_access.iter().min_by_key(|&(_, &access)| access)
-                {
-                    if lru_chunk != chunk_idx {
-                        self.unload_chunk(lru_chunk)?;
-                    }
-                }
-            }
-        }
+    /// One past the last item to render, including buffer and overscan.
+    #[wasm_bindgen(getter)]
+    pub fn render_end(&self) -> usize {
+        self.render_end
+    }
 
-        // Now safely create or access the chunk
-        if self.chunks[chunk_idx].is_none() {
-            let items_in_chunk =
-                if chunk_idx == self.chunks.len() - 1 && self.total_items % self.chunk_size != 0 {
-                    self.total_items % self.chunk_size
-                } else {
-                    self.chunk_size
-                };
-            self.chunks[chunk_idx] = Some(
-                Chunk::new(items_in_chunk, self.estimated_size)
-                    .map_err(|e| convert_error("ChunkCreationError", &e))?,
-            );
-        }
+    /// Leading edge of item `render_start`.
+    #[wasm_bindgen(getter)]
+    pub fn render_start_offset(&self) -> f64 {
+        self.render_start_offset
+    }
 
-        let chunk = self.chunks[chunk_idx].as_mut().unwrap();
+    /// Trailing edge of item `render_end - 1`.
+    #[wasm_bindgen(getter)]
+    pub fn render_end_offset(&self) -> f64 {
+        self.render_end_offset
+    }
+}
 
-        // Update access tracking
-        self.access_counter += 1;
-        self.chunk_access.insert(chunk_idx, self.access_counter);
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ItemHit {
+    index: usize,
+    offset: f64,
+}
 
-        Ok(chunk)
+#[wasm_bindgen]
+impl ItemHit {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
     }
 
-    #[wasm_bindgen]
-    pub fn update_item_size(&mut self, index: usize, new_size: f64) -> Result<(), JsValue> {
-        if index >= self.total_items {
-            return Err(convert_error(
-                "IndexOutOfBounds",
-                &format!("Index {} exceeds total items", index),
-            ));
-        }
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ItemBoundary {
+    index: usize,
+    offset: f64,
+}
+
+#[wasm_bindgen]
+impl ItemBoundary {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Leading edge of item `index`.
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ItemBounds {
+    start: f64,
+    size: f64,
+    end: f64,
+}
+
+#[wasm_bindgen]
+impl ItemBounds {
+    /// Leading edge of the item.
+    #[wasm_bindgen(getter)]
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// Trailing edge of the item.
+    #[wasm_bindgen(getter)]
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct VisibleItem {
+    index: usize,
+    offset: f64,
+    size: f64,
+}
+
+#[wasm_bindgen]
+impl VisibleItem {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Leading edge of the item, in scroll coordinates.
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ChunkRange {
+    chunk_index: usize,
+    start_index: usize,
+    end_index: usize,
+    start_offset: f64,
+    end_offset: f64,
+}
+
+#[wasm_bindgen]
+impl ChunkRange {
+    #[wasm_bindgen(getter)]
+    pub fn chunk_index(&self) -> usize {
+        self.chunk_index
+    }
+
+    /// First item in the chunk.
+    #[wasm_bindgen(getter)]
+    pub fn start_index(&self) -> usize {
+        self.start_index
+    }
+
+    /// One past the last item in the chunk.
+    #[wasm_bindgen(getter)]
+    pub fn end_index(&self) -> usize {
+        self.end_index
+    }
+
+    /// Leading edge of item `start_index`.
+    #[wasm_bindgen(getter)]
+    pub fn start_offset(&self) -> f64 {
+        self.start_offset
+    }
+
+    /// Trailing edge of item `end_index - 1`.
+    #[wasm_bindgen(getter)]
+    pub fn end_offset(&self) -> f64 {
+        self.end_offset
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct RangeDelta {
+    added: Vec<u32>,
+    removed: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl RangeDelta {
+    /// Indices to mount, in order.
+    #[wasm_bindgen(getter)]
+    pub fn added(&self) -> Vec<u32> {
+        self.added.clone()
+    }
+
+    /// Indices to unmount, in order.
+    #[wasm_bindgen(getter)]
+    pub fn removed(&self) -> Vec<u32> {
+        self.removed.clone()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct StickyHeader {
+    section: Option<usize>,
+    offset: f64,
+}
+
+#[wasm_bindgen]
+impl StickyHeader {
+    /// Section whose header should stick, or `None` above the first header.
+    #[wasm_bindgen(getter)]
+    pub fn section(&self) -> Option<usize> {
+        self.section
+    }
+
+    /// Where to render the sticky header relative to the viewport's leading
+    /// edge: 0 normally, negative while the next header pushes it out.
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
+
+// Scroll and viewport changes below this reuse the last `get_visible_range`
+const RANGE_CACHE_EPSILON: f64 = 1e-6;
+
+// Relative change in the running mean before `use_dynamic_estimate`
+// re-estimates the unloaded chunks
+const DYNAMIC_ESTIMATE_TOLERANCE: f64 = 0.01;
+
+// Longest list `debug_state` prints in full
+const DEBUG_LIST_LIMIT: usize = 32;
+
+// Smallest thumb reported by `scrollbar_metrics`, as a fraction of the track,
+// so very long lists still get a grabbable thumb.
+const MIN_THUMB_SIZE_RATIO: f64 = 0.02;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ScrollbarMetrics {
+    thumb_size_ratio: f64,
+    thumb_offset_ratio: f64,
+}
+
+#[wasm_bindgen]
+impl ScrollbarMetrics {
+    /// Thumb length as a fraction of the scrollbar track.
+    #[wasm_bindgen(getter)]
+    pub fn thumb_size_ratio(&self) -> f64 {
+        self.thumb_size_ratio
+    }
+
+    /// How far the thumb has travelled, from 0.0 (top) to 1.0 (bottom).
+    #[wasm_bindgen(getter)]
+    pub fn thumb_offset_ratio(&self) -> f64 {
+        self.thumb_offset_ratio
+    }
+}
+
+#[derive(Clone)]
+struct Chunk {
+    sizes: ChunkSizes,
+    total_size: f64,
+    // Whether each item's size was explicitly set rather than estimated
+    measured: MeasuredFlags,
+}
+
+// Item sizes of a chunk, stored densely or as runs of equal sizes depending
+// on `VirtualListConfig::run_length_chunks`.
+#[derive(Clone)]
+enum ChunkSizes {
+    Dense(DenseSizes),
+    RunLength(RleSizes),
+}
+
+impl Chunk {
+    fn new(sizes: Vec<f64>, run_length: bool) -> Result<Self, String> {
+        if let Some(&size) = sizes.iter().find(|size| !is_valid_size(**size)) {
+            return Err(format!("Invalid size: {}", size));
+        }
+        let (sizes, measured) = if run_length {
+            (
+                ChunkSizes::RunLength(RleSizes::new(&sizes)),
+                MeasuredFlags::Runs(MeasuredRuns::new(sizes.len())),
+            )
+        } else {
+            let measured = MeasuredFlags::Dense(vec![false; sizes.len()]);
+            (ChunkSizes::Dense(DenseSizes::new(sizes)), measured)
+        };
+        let mut chunk = Chunk {
+            sizes,
+            total_size: 0.0,
+            measured,
+        };
+        chunk.total_size = chunk.prefix(chunk.len());
+        Ok(chunk)
+    }
+
+    fn len(&self) -> usize {
+        match &self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.sizes.len(),
+            ChunkSizes::RunLength(sizes) => sizes.len,
+        }
+    }
+
+    fn size(&self, index: usize) -> f64 {
+        match &self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.sizes[index],
+            ChunkSizes::RunLength(sizes) => sizes.get(index),
+        }
+    }
+
+    fn iter_sizes(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.len()).map(|index| self.size(index))
+    }
+
+    // Sum of the first `count` sizes.
+    fn prefix(&self, count: usize) -> f64 {
+        match &self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.prefix(count),
+            ChunkSizes::RunLength(sizes) => sizes.prefix(count),
+        }
+    }
+
+    // Truncates to `len` items, then appends `tail` as unmeasured sizes.
+    fn resize(&mut self, len: usize, tail: Vec<f64>) {
+        self.measured
+            .resize(len.min(self.len()), len.min(self.len()) + tail.len());
+        match &mut self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.resize(len, tail),
+            ChunkSizes::RunLength(sizes) => {
+                sizes.truncate(len);
+                for size in tail {
+                    sizes.push(size);
+                }
+            }
+        }
+        self.total_size = self.prefix(self.len());
+    }
+
+    fn update_size(&mut self, index: usize, new_size: f64) -> Result<f64, String> {
+        let diff = self.set_size(index, new_size)?;
+        self.measured.set(index, true);
+        Ok(diff)
+    }
+
+    // Like `update_size`, but leaves the measured flag untouched.
+    fn set_size(&mut self, index: usize, new_size: f64) -> Result<f64, String> {
+        if index >= self.len() {
+            return Err(format!("Index {} out of bounds", index));
+        }
+        if !is_valid_size(new_size) {
+            return Err(format!("Invalid size: {}", new_size));
+        }
+        let old_size = match &mut self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.set(index, new_size),
+            ChunkSizes::RunLength(sizes) => sizes.set(index, new_size),
+        };
+        let diff = new_size - old_size;
+        self.total_size += diff;
+        Ok(diff)
+    }
+
+    // `extra_before(i)` is the non-item space (e.g. gaps) laid out before
+    // item `i`, relative to the chunk's first item.
+    fn find_item_at_position(
+        &self,
+        position: f64,
+        extra_before: impl Fn(usize) -> f64,
+    ) -> Result<(usize, f64), String> {
+        if position.is_nan() || position < 0.0 || self.len() == 0 {
+            return Err(format!("Invalid position: {}", position));
+        }
+        Ok(match &self.sizes {
+            ChunkSizes::Dense(sizes) => sizes.find(position, extra_before),
+            ChunkSizes::RunLength(sizes) => find_in_prefix_sums(
+                sizes.len,
+                |count| sizes.prefix(count),
+                extra_before,
+                position,
+            ),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct DenseSizes {
+    sizes: Vec<f64>,
+    // Fenwick tree over `sizes` (1-based): `tree[i]` holds the sum of the
+    // `i & i.wrapping_neg()` sizes ending at item `i - 1`.
+    tree: Vec<f64>,
+}
+
+impl DenseSizes {
+    fn new(sizes: Vec<f64>) -> Self {
+        let mut dense = DenseSizes {
+            sizes,
+            tree: Vec::new(),
+        };
+        dense.rebuild_tree();
+        dense
+    }
+
+    fn resize(&mut self, len: usize, tail: Vec<f64>) {
+        self.sizes.truncate(len);
+        self.sizes.extend(tail);
+        self.rebuild_tree();
+    }
+
+    // Builds the tree in O(n) from running sums: node `i` covers the items
+    // between `i` with its lowest set bit cleared and `i`.
+    fn rebuild_tree(&mut self) {
+        let mut running = Vec::with_capacity(self.sizes.len() + 1);
+        running.push(0.0);
+        push_running_sums(&self.sizes, 0.0, &mut running);
+        self.tree = (0..running.len())
+            .map(|i| {
+                if i == 0 {
+                    0.0
+                } else {
+                    running[i] - running[i & (i - 1)]
+                }
+            })
+            .collect();
+    }
+
+    fn prefix(&self, count: usize) -> f64 {
+        let mut i = count.min(self.sizes.len());
+        let mut sum = 0.0;
+        while i > 0 {
+            sum += self.tree[i];
+            i &= i - 1;
+        }
+        sum
+    }
+
+    // Returns the previous size.
+    fn set(&mut self, index: usize, new_size: f64) -> f64 {
+        let old_size = std::mem::replace(&mut self.sizes[index], new_size);
+        let diff = new_size - old_size;
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += diff;
+            i += i & i.wrapping_neg();
+        }
+        old_size
+    }
+
+    // Leading edges never decrease, so the slot containing `position` is
+    // found by descending the tree rather than bisecting over prefix queries.
+    fn find(&self, position: f64, extra_before: impl Fn(usize) -> f64) -> (usize, f64) {
+        let len = self.sizes.len();
+        // Largest `count` whose first `count` slots end at or before `position`
+        let (mut count, mut sum) = (0, 0.0);
+        let mut step = 1 << len.ilog2();
+        while step > 0 {
+            let next = count + step;
+            if next <= len && sum + self.tree[next] + extra_before(next) <= position {
+                count = next;
+                sum += self.tree[next];
+            }
+            step >>= 1;
+        }
+        let index = count.min(len - 1);
+        let start = if index == count {
+            sum
+        } else {
+            self.prefix(index)
+        };
+        (index, position - (start + extra_before(index)))
+    }
+}
+
+// Run-length encoded sizes for mostly uniform chunks: memory grows with the
+// number of distinct runs rather than items. Lookups bisect the runs; size
+// changes rewrite the run list, so they are O(runs).
+#[derive(Clone)]
+struct RleSizes {
+    len: usize,
+    // (first item, size) of each run of equal sizes, by first item
+    runs: Vec<(usize, f64)>,
+    // run_sums[k] is the combined size of the items before run k
+    run_sums: Vec<f64>,
+}
+
+impl RleSizes {
+    fn new(sizes: &[f64]) -> Self {
+        let mut rle = RleSizes {
+            len: 0,
+            runs: Vec::new(),
+            run_sums: Vec::new(),
+        };
+        for &size in sizes {
+            rle.push(size);
+        }
+        rle
+    }
+
+    // Run containing item `index`
+    fn run_at(&self, index: usize) -> usize {
+        self.runs.partition_point(|&(start, _)| start <= index) - 1
+    }
+
+    fn get(&self, index: usize) -> f64 {
+        self.runs[self.run_at(index)].1
+    }
+
+    fn prefix(&self, count: usize) -> f64 {
+        let count = count.min(self.len);
+        if count == 0 {
+            return 0.0;
+        }
+        let run = self.run_at(count - 1);
+        let (start, size) = self.runs[run];
+        self.run_sums[run] + (count - start) as f64 * size
+    }
+
+    fn push(&mut self, size: f64) {
+        if self.runs.last().is_none_or(|&(_, last)| last != size) {
+            self.run_sums.push(self.prefix(self.len));
+            self.runs.push((self.len, size));
+        }
+        self.len += 1;
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let keep = if len == 0 {
+            0
+        } else {
+            self.run_at(len - 1) + 1
+        };
+        self.runs.truncate(keep);
+        self.run_sums.truncate(keep);
+        self.len = len;
+    }
+
+    // Splits the run holding `index` around it, then merges equal
+    // neighbours. Returns the previous size.
+    fn set(&mut self, index: usize, new_size: f64) -> f64 {
+        let run = self.run_at(index);
+        let (start, old_size) = self.runs[run];
+        if old_size == new_size {
+            return old_size;
+        }
+        let end = self.runs.get(run + 1).map_or(self.len, |&(start, _)| start);
+        let mut replacement = Vec::with_capacity(3);
+        if start < index {
+            replacement.push((start, old_size));
+        }
+        replacement.push((index, new_size));
+        if index + 1 < end {
+            replacement.push((index + 1, old_size));
+        }
+        self.runs.splice(run..=run, replacement);
+        self.runs.dedup_by(|next, previous| next.1 == previous.1);
+        let mut sum = 0.0;
+        self.run_sums.clear();
+        for (k, &(start, size)) in self.runs.iter().enumerate() {
+            self.run_sums.push(sum);
+            let end = self.runs.get(k + 1).map_or(self.len, |&(start, _)| start);
+            sum += (end - start) as f64 * size;
+        }
+        old_size
+    }
+}
+
+// Per-item measured flags. Run-length chunks keep them as runs too, so a
+// mostly uniform chunk stays O(runs) however many items it holds.
+#[derive(Clone)]
+enum MeasuredFlags {
+    Dense(Vec<bool>),
+    Runs(MeasuredRuns),
+}
+
+impl MeasuredFlags {
+    fn get(&self, index: usize) -> bool {
+        match self {
+            MeasuredFlags::Dense(flags) => flags[index],
+            MeasuredFlags::Runs(runs) => runs.get(index),
+        }
+    }
+
+    fn set(&mut self, index: usize, measured: bool) {
+        match self {
+            MeasuredFlags::Dense(flags) => flags[index] = measured,
+            MeasuredFlags::Runs(runs) => runs.set(index, measured),
+        }
+    }
+
+    fn fill(&mut self, measured: bool) {
+        match self {
+            MeasuredFlags::Dense(flags) => flags.fill(measured),
+            MeasuredFlags::Runs(runs) => runs.fill(measured),
+        }
+    }
+
+    // Truncates to `keep` flags, then pads with unmeasured ones up to `len`.
+    fn resize(&mut self, keep: usize, len: usize) {
+        match self {
+            MeasuredFlags::Dense(flags) => {
+                flags.truncate(keep);
+                flags.resize(len, false);
+            }
+            MeasuredFlags::Runs(runs) => {
+                runs.truncate(keep);
+                runs.extend_unmeasured(len);
+            }
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        let len = match self {
+            MeasuredFlags::Dense(flags) => flags.len(),
+            MeasuredFlags::Runs(runs) => runs.len,
+        };
+        (0..len).map(|index| self.get(index))
+    }
+}
+
+// Measured flags as the sorted indices where the flag flips, starting from
+// unmeasured: items between the k-th and (k+1)-th flip are measured when k
+// is even.
+#[derive(Clone)]
+struct MeasuredRuns {
+    len: usize,
+    flips: Vec<usize>,
+}
+
+impl MeasuredRuns {
+    fn new(len: usize) -> Self {
+        MeasuredRuns {
+            len,
+            flips: Vec::new(),
+        }
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.flips.partition_point(|&flip| flip <= index) % 2 == 1
+    }
+
+    fn set(&mut self, index: usize, measured: bool) {
+        if self.get(index) == measured {
+            return;
+        }
+        // Flipping just `index` toggles the boundaries on both sides of it
+        for boundary in [index, index + 1] {
+            if boundary == self.len {
+                continue;
+            }
+            match self.flips.binary_search(&boundary) {
+                Ok(at) => {
+                    self.flips.remove(at);
+                }
+                Err(at) => self.flips.insert(at, boundary),
+            }
+        }
+    }
+
+    fn fill(&mut self, measured: bool) {
+        self.flips.clear();
+        if measured && self.len > 0 {
+            self.flips.push(0);
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.flips.retain(|&flip| flip < len);
+            self.len = len;
+        }
+    }
+
+    fn extend_unmeasured(&mut self, len: usize) {
+        if len > self.len && self.flips.len() % 2 == 1 {
+            self.flips.push(self.len);
+        }
+        self.len = self.len.max(len);
+    }
+}
+
+// Every item size and estimate goes through this: NaN, infinities and
+// negatives would corrupt the offsets, and infinities don't survive
+// `export_sizes_json`.
+fn is_valid_size(size: f64) -> bool {
+    size.is_finite() && size >= 0.0
+}
+
+// Appends `start + sizes[..=i]` to `out` for every `i`.
+fn push_running_sums(sizes: &[f64], start: f64, out: &mut Vec<f64>) {
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    push_running_sums_simd(sizes, start, out);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    push_running_sums_scalar(sizes, start, out);
+}
+
+#[cfg(any(test, not(all(target_arch = "wasm32", target_feature = "simd128"))))]
+fn push_running_sums_scalar(sizes: &[f64], start: f64, out: &mut Vec<f64>) {
+    let mut cumulative = start;
+    for &size in sizes {
+        cumulative += size;
+        out.push(cumulative);
+    }
+}
+
+// SIMD variant: each pair [a, b] becomes [a, a + b] with one shuffle and add,
+// then the running total is added to both lanes. Sums are associated
+// differently from the scalar loop, so results may differ in the last bits.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn push_running_sums_simd(sizes: &[f64], start: f64, out: &mut Vec<f64>) {
+    use std::arch::wasm32::{f64x2, f64x2_add, f64x2_extract_lane, f64x2_splat, i64x2_shuffle};
+    let zero = f64x2_splat(0.0);
+    let mut cumulative = start;
+    let pairs = sizes.chunks_exact(2);
+    let remainder = pairs.remainder();
+    for pair in pairs {
+        let values = f64x2(pair[0], pair[1]);
+        // [0, a]: lane 0 of `zero`, lane 0 of `values`
+        let shifted = i64x2_shuffle::<0, 2>(zero, values);
+        let sums = f64x2_add(f64x2_add(values, shifted), f64x2_splat(cumulative));
+        out.push(f64x2_extract_lane::<0>(sums));
+        cumulative = f64x2_extract_lane::<1>(sums);
+        out.push(cumulative);
+    }
+    for &size in remainder {
+        cumulative += size;
+        out.push(cumulative);
+    }
+}
+
+// Locates `position` among `len` items whose leading edges are
+// `prefix(i) + extra_before(i)`: the first item whose slot (the item plus any
+// space before the next one) ends past `position`. Positions past the end
+// (including rounding drift against the total) resolve to the last item.
+fn find_in_prefix_sums(
+    len: usize,
+    prefix: impl Fn(usize) -> f64,
+    extra_before: impl Fn(usize) -> f64,
+    position: f64,
+) -> (usize, f64) {
+    let index =
+        partition_point_by(len, |i| prefix(i + 1) + extra_before(i + 1) <= position).min(len - 1);
+    let offset = position - (prefix(index) + extra_before(index));
+    (index, offset)
+}
+
+// Formats `items` for `debug_state`, eliding everything past the limit.
+fn debug_list<T: std::fmt::Debug>(items: &[T]) -> String {
+    if items.len() <= DEBUG_LIST_LIMIT {
+        return format!("{:?}", items);
+    }
+    format!(
+        "{:?} ... ({} more)",
+        &items[..DEBUG_LIST_LIMIT],
+        items.len() - DEBUG_LIST_LIMIT
+    )
+}
+
+const STATE_FORMAT_VERSION: u8 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct SizesExport {
+    chunk_size: usize,
+    total_items: usize,
+    sizes: BTreeMap<usize, f64>,
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.bytes.len() < N {
+            return Err("Unexpected end of state".to_string());
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, String> {
+        let value = self.read_u64()?;
+        usize::try_from(value).map_err(|_| format!("Value {} does not fit in usize", value))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn read_f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    // A length-prefixed list of `(index, size)` pairs with indices below `len`.
+    fn read_entries(&mut self, len: usize) -> Result<Vec<(usize, f64)>, String> {
+        let count = self.read_usize()?;
+        let mut entries = Vec::with_capacity(count.min(self.bytes.len() / 16));
+        for _ in 0..count {
+            let index = self.read_usize()?;
+            let size = self.read_f64()?;
+            if index >= len {
+                return Err(format!("Index {} out of bounds", index));
+            }
+            if !is_valid_size(size) {
+                return Err(format!("Invalid size: {}", size));
+            }
+            entries.push((index, size));
+        }
+        Ok(entries)
+    }
+}
+
+// Last `get_visible_range` inputs and result, with the generation it was
+// computed at.
+#[derive(Clone)]
+struct RangeCache {
+    scroll: f64,
+    viewport: f64,
+    generation: u64,
+    range: VisibleRange,
+    // Last scroll at which `range` was the unheld result, so hysteresis is
+    // measured from the boundary that changed it rather than the previous
+    // call
+    anchor: f64,
+}
+
+// Running chunk totals. Each chunk counts as its item count times `base`
+// plus a deviation kept in a Fenwick tree, so a list whose chunks all match
+// the uniform estimate needs no per-chunk storage. The tree is allocated on
+// the first deviation.
+#[derive(Clone)]
+struct ChunkOffsets {
+    total_items: usize,
+    chunk_size: usize,
+    num_chunks: usize,
+    base: f64,
+    // Fenwick tree (1-based) over per-chunk deviations from the uniform
+    // total; empty while there are none.
+    tree: Vec<f64>,
+}
+
+impl ChunkOffsets {
+    fn uniform(total_items: usize, chunk_size: usize, base: f64) -> Self {
+        ChunkOffsets {
+            total_items,
+            chunk_size,
+            num_chunks: total_items.div_ceil(chunk_size),
+            base,
+            tree: Vec::new(),
+        }
+    }
+
+    // Builds the tree in O(chunks) from the actual chunk totals.
+    fn with_totals(total_items: usize, chunk_size: usize, base: f64, totals: &[f64]) -> Self {
+        let mut offsets = ChunkOffsets::uniform(total_items, chunk_size, base);
+        let deviations: Vec<f64> = totals
+            .iter()
+            .enumerate()
+            .map(|(chunk_idx, &total)| total - offsets.uniform_total(chunk_idx))
+            .collect();
+        if deviations.iter().any(|&deviation| deviation != 0.0) {
+            let mut running = Vec::with_capacity(deviations.len() + 1);
+            running.push(0.0);
+            push_running_sums(&deviations, 0.0, &mut running);
+            offsets.tree = (0..running.len())
+                .map(|i| {
+                    if i == 0 {
+                        0.0
+                    } else {
+                        running[i] - running[i & (i - 1)]
+                    }
+                })
+                .collect();
+        }
+        offsets
+    }
+
+    fn len(&self) -> usize {
+        self.num_chunks
+    }
+
+    fn uniform_total(&self, chunk_idx: usize) -> f64 {
+        self.uniform_prefix(chunk_idx + 1) - self.uniform_prefix(chunk_idx)
+    }
+
+    fn uniform_prefix(&self, count: usize) -> f64 {
+        count.saturating_mul(self.chunk_size).min(self.total_items) as f64 * self.base
+    }
+
+    // Combined total of the first `count` chunks.
+    fn prefix(&self, count: usize) -> f64 {
+        let count = count.min(self.num_chunks);
+        self.uniform_prefix(count) + self.deviation_prefix(count)
+    }
+
+    fn deviation_prefix(&self, count: usize) -> f64 {
+        let mut sum = 0.0;
+        if !self.tree.is_empty() {
+            let mut i = count;
+            while i > 0 {
+                sum += self.tree[i];
+                i &= i - 1;
+            }
+        }
+        sum
+    }
+
+    // How far chunk `chunk_idx` is from its uniform total.
+    fn deviation(&self, chunk_idx: usize) -> f64 {
+        if chunk_idx >= self.num_chunks {
+            return 0.0;
+        }
+        self.deviation_prefix(chunk_idx + 1) - self.deviation_prefix(chunk_idx)
+    }
+
+    // Changes the item count in place. Surviving chunks keep their
+    // deviations and new ones start at the uniform total, so callers only
+    // need to correct the chunks whose length changed.
+    fn resize(&mut self, total_items: usize) {
+        let num_chunks = total_items.div_ceil(self.chunk_size);
+        if !self.tree.is_empty() {
+            if num_chunks < self.num_chunks {
+                self.tree.truncate(num_chunks + 1);
+            } else {
+                // Node `i` covers chunks `(i & (i - 1))..i`, all of which but
+                // the new, zero-deviation last one are already in the tree
+                for i in self.num_chunks + 1..=num_chunks {
+                    let node = self.deviation_prefix(i - 1) - self.deviation_prefix(i & (i - 1));
+                    self.tree.push(node);
+                }
+            }
+        }
+        self.total_items = total_items;
+        self.num_chunks = num_chunks;
+    }
+
+    fn add(&mut self, chunk_idx: usize, diff: f64) {
+        if diff == 0.0 {
+            return;
+        }
+        if self.tree.is_empty() {
+            self.tree = vec![0.0; self.num_chunks + 1];
+        }
+        let mut i = chunk_idx + 1;
+        while i < self.tree.len() {
+            self.tree[i] += diff;
+            i += i & i.wrapping_neg();
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct VirtualList {
+    total_items: usize,
+    estimated_size: f64,
+    orientation: Orientation,
+    // Loaded chunks only, so a new list does no per-chunk work
+    chunks: BTreeMap<usize, Chunk>,
+    chunk_size: usize,
+    chunk_offsets: ChunkOffsets,
+    total_size: f64,
+    config: VirtualListConfig,
+    access_counter: u64,
+    chunk_access: HashMap<usize, u64>,
+    size_hints: HashMap<usize, f64>,
+    chunk_estimates: HashMap<usize, f64>,
+    measured_count: u64,
+    measured_sum: f64,
+    // Running mean as of the last re-estimate; what `use_dynamic_estimate`
+    // lays unloaded chunks out with
+    dynamic_estimate: f64,
+    frozen: bool,
+    on_chunk_loaded: Option<Function>,
+    on_chunk_unloaded: Option<Function>,
+    // (index of the item the header sits before, header size), sorted by index
+    sections: Vec<(usize, f64)>,
+    // section_header_sums[k] is the total size of the first k headers
+    section_header_sums: Vec<f64>,
+    // Sorted indices of items rendered outside the scrolled flow
+    pinned: Vec<usize>,
+    kind_estimates: HashMap<u32, f64>,
+    item_kinds: HashMap<usize, u32>,
+    // Bumped by every layout change
+    generation: u64,
+    // Set once `generation` has been read since its last bump. Until then an
+    // eviction is already covered by that bump and needs none of its own.
+    generation_seen: Cell<bool>,
+    range_cache: Option<RangeCache>,
+}
+
+#[wasm_bindgen]
+impl VirtualList {
+    /// `total_items` may be 0. An empty list has no chunks: size queries
+    /// return 0 and anything that needs an item fails with `EmptyList` or
+    /// `IndexOutOfBounds` until `set_total_items` grows it.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        total_items: usize,
+        chunk_size: usize,
+        estimated_size: f64,
+        orientation: Orientation,
+        config: VirtualListConfig,
+    ) -> Result<VirtualList, VirtualListError> {
+        if chunk_size == 0 {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidConfig,
+                "chunk_size must be positive",
+            ));
+        }
+        if !is_valid_size(estimated_size) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid estimated size: {}", estimated_size),
+            ));
+        }
+
+        let mut list = VirtualList {
+            total_items,
+            estimated_size,
+            orientation,
+            chunks: BTreeMap::new(),
+            chunk_size,
+            chunk_offsets: ChunkOffsets::uniform(total_items, chunk_size, estimated_size),
+            total_size: 0.0,
+            config,
+            access_counter: 0,
+            chunk_access: HashMap::new(),
+            size_hints: HashMap::new(),
+            chunk_estimates: HashMap::new(),
+            measured_count: 0,
+            measured_sum: 0.0,
+            dynamic_estimate: estimated_size,
+            frozen: false,
+            on_chunk_loaded: None,
+            on_chunk_unloaded: None,
+            sections: Vec::new(),
+            section_header_sums: vec![0.0],
+            pinned: Vec::new(),
+            kind_estimates: HashMap::new(),
+            item_kinds: HashMap::new(),
+            generation: 0,
+            generation_seen: Cell::new(false),
+            range_cache: None,
+        };
+        list.rebuild_cumulative_sizes();
+        Ok(list)
+    }
+
+    /// Builds a list whose sizes are all known up front, with every item
+    /// marked measured. The mean size becomes the estimate for items added
+    /// later. `max_loaded_chunks` applies as usual: only the first that many
+    /// chunks are materialized, and each of the rest is estimated at its own
+    /// mean size, so chunk boundaries stay exact but positions inside those
+    /// chunks may not be. Disable the cap to keep every size.
+    #[wasm_bindgen]
+    pub fn with_sizes(
+        sizes: &[f64],
+        chunk_size: usize,
+        orientation: Orientation,
+        config: VirtualListConfig,
+    ) -> Result<VirtualList, VirtualListError> {
+        if let Some(&size) = sizes.iter().find(|size| !is_valid_size(**size)) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size: {}", size),
+            ));
+        }
+        let sizes: Vec<f64> = sizes
+            .iter()
+            .map(|&size| size.max(config.min_item_size))
+            .collect();
+        let sum: f64 = sizes.iter().sum();
+        let estimated_size = if sizes.is_empty() {
+            0.0
+        } else {
+            sum / sizes.len() as f64
+        };
+        let mut list =
+            VirtualList::new(sizes.len(), chunk_size, estimated_size, orientation, config)?;
+        let loaded = list.config.max_loaded_chunks.unwrap_or(usize::MAX);
+        for (chunk_idx, chunk_sizes) in sizes.chunks(chunk_size).enumerate() {
+            if chunk_idx >= loaded {
+                let chunk_sum: f64 = chunk_sizes.iter().sum();
+                list.chunk_estimates
+                    .insert(chunk_idx, chunk_sum / chunk_sizes.len() as f64);
+                continue;
+            }
+            let mut chunk = Chunk::new(chunk_sizes.to_vec(), list.config.run_length_chunks)
+                .map_err(|e| convert_error(VirtualListErrorKind::ChunkCreationError, &e))?;
+            chunk.measured.fill(true);
+            list.chunks.insert(chunk_idx, chunk);
+            list.access_counter += 1;
+            list.chunk_access.insert(chunk_idx, list.access_counter);
+        }
+        list.measured_count = sizes.len() as u64;
+        list.measured_sum = sum;
+        list.rebuild_cumulative_sizes();
+        Ok(list)
+    }
+
+    /// Running totals of item sizes per chunk: entry `k` is the combined
+    /// size of chunks `0..=k`. Per chunk, not per item; unloaded chunks count
+    /// at their estimates. Gaps, headers and padding are not included.
+    #[wasm_bindgen]
+    pub fn cumulative_sizes(&self) -> Vec<f64> {
+        (1..=self.chunk_offsets.len())
+            .map(|count| self.chunk_offsets.prefix(count))
+            .collect()
+    }
+
+    /// Per-item prefix sums: entry `i` is the combined size of items `0..i`,
+    /// with `total_items + 1` entries. Without `load_chunks`, items in
+    /// unloaded chunks share their chunk's estimate evenly. With it, each
+    /// chunk is loaded in turn, subject to `max_loaded_chunks` eviction.
+    #[wasm_bindgen]
+    pub fn item_prefix_sums(&mut self, load_chunks: bool) -> Result<Vec<f64>, VirtualListError> {
+        if !load_chunks {
+            return Ok((0..=self.total_items)
+                .map(|index| self.peek_sizes_before(index))
+                .collect());
+        }
+        let mut sums = Vec::with_capacity(self.total_items + 1);
+        sums.push(0.0);
+        let mut base = 0.0;
+        for chunk_idx in 0..self.num_chunks() {
+            let len = self.items_in_chunk(chunk_idx);
+            let chunk = self.get_or_create_chunk(chunk_idx)?;
+            sums.extend((1..=len).map(|i| base + chunk.prefix(i)));
+            base += chunk.total_size;
+        }
+        Ok(sums)
+    }
+
+    /// Index ranges and pixel extents of every chunk, in order. Never loads
+    /// a chunk: unloaded chunks contribute their estimated totals.
+    #[wasm_bindgen]
+    pub fn chunk_ranges(&self) -> Vec<ChunkRange> {
+        (0..self.num_chunks())
+            .map(|chunk_index| {
+                let start_index = chunk_index * self.chunk_size;
+                let end_index = (start_index + self.chunk_size).min(self.total_items);
+                ChunkRange {
+                    chunk_index,
+                    start_index,
+                    end_index,
+                    start_offset: self.peek_item_offset(start_index),
+                    end_offset: self.peek_item_end_offset(end_index - 1),
+                }
+            })
+            .collect()
+    }
+
+    /// Deep copy for speculative changes: mutate the snapshot and discard it
+    /// to roll back. Chunk access order carries over, so both evict alike.
+    /// Chunk callbacks are shared with the original.
+    #[wasm_bindgen]
+    pub fn snapshot(&self) -> VirtualList {
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    #[wasm_bindgen]
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Incremented once by every call that changes the layout, so callers
+    /// can skip recomputing when it is unchanged between frames. Chunk
+    /// evictions count as changes; calls that don't change anything, like
+    /// setting the current item count again, leave it as is.
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> u64 {
+        self.generation_seen.set(true);
+        self.generation
+    }
+
+    // Called first by every method that can change the layout: fails on a
+    // frozen list, otherwise bumps the generation so cached results are
+    // recomputed. Each public method bumps it once, so helpers shared
+    // between them must not call this.
+    fn begin_mutation(&mut self) -> Result<(), VirtualListError> {
+        self.check_not_frozen()?;
+        self.generation += 1;
+        self.generation_seen.set(false);
+        Ok(())
+    }
+
+    fn check_not_frozen(&self) -> Result<(), VirtualListError> {
+        if self.frozen {
+            return Err(convert_error(
+                VirtualListErrorKind::Frozen,
+                "List is frozen",
+            ));
+        }
+        Ok(())
+    }
+
+    fn get_or_create_chunk(&mut self, chunk_idx: usize) -> Result<&mut Chunk, VirtualListError> {
+        if chunk_idx >= self.num_chunks() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidChunkIndex,
+                &format!("Chunk index {} out of bounds", chunk_idx),
+            ));
+        }
+
+        // Handle unloading before borrowing the chunk. A frozen list may still
+        // materialize chunks but must not drop measurements, so it skips eviction.
+        if !self.frozen
+            && let Some(max) = self.config.max_loaded_chunks
+            && self.chunk_access.len() >= max
+            && !self.chunk_access.contains_key(&chunk_idx)
+            && let Some((&lru_chunk, _)) =
+                self.chunk_access.iter().min_by_key(|&(_, &access)| access)
+            && lru_chunk != chunk_idx
+        {
+            self.drop_chunk(lru_chunk)?;
+            if self.generation_seen.replace(false) {
+                self.generation += 1;
+            }
+        }
+
+        // Now safely create or access the chunk
+        let created = !self.chunks.contains_key(&chunk_idx);
+        self.create_chunk(chunk_idx)?;
+
+        // Update access tracking
+        self.access_counter += 1;
+        self.chunk_access.insert(chunk_idx, self.access_counter);
+
+        if created && self.on_chunk_loaded.is_some() {
+            let len = self.chunks[&chunk_idx].len();
+            self.notify_chunk_callback(true, chunk_idx, len)?;
+        }
+
+        Ok(self.chunks.get_mut(&chunk_idx).unwrap())
+    }
+
+    /// Called as `callback(chunk_index, start_index, end_index)` whenever a
+    /// chunk is materialized. Pass `undefined` to remove it.
+    #[wasm_bindgen]
+    pub fn set_on_chunk_loaded(&mut self, callback: Option<Function>) {
+        self.on_chunk_loaded = callback;
+    }
+
+    /// Called as `callback(chunk_index, start_index, end_index)` whenever a
+    /// chunk is unloaded, including LRU eviction. Pass `undefined` to remove it.
+    #[wasm_bindgen]
+    pub fn set_on_chunk_unloaded(&mut self, callback: Option<Function>) {
+        self.on_chunk_unloaded = callback;
+    }
+
+    /// Deterministic cleanup before the object is garbage collected:
+    /// releases every chunk and per-item setting, leaving an empty list, then
+    /// reports the released chunks to `on_chunk_unloaded` and drops both
+    /// chunk callbacks. Like other mutations it fails on a frozen list;
+    /// unfreeze it first.
+    #[wasm_bindgen]
+    pub fn dispose(&mut self) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        let released = self.loaded_chunk_spans(false, |_| true);
+        self.total_items = 0;
+        self.chunks = BTreeMap::new();
+        self.chunk_access = HashMap::new();
+        self.size_hints = HashMap::new();
+        self.chunk_estimates = HashMap::new();
+        self.kind_estimates = HashMap::new();
+        self.item_kinds = HashMap::new();
+        self.pinned = Vec::new();
+        self.replace_sections(Vec::new());
+        self.range_cache = None;
+        self.rebuild_cumulative_sizes();
+        let result = self.notify_chunk_spans(false, released);
+        self.on_chunk_loaded = None;
+        self.on_chunk_unloaded = None;
+        result
+    }
+
+    // A callback cannot reach the list while it runs: wasm-bindgen rejects
+    // the nested borrow, so callbacks never nest.
+    fn notify_chunk_callback(
+        &self,
+        loaded: bool,
+        chunk_idx: usize,
+        len: usize,
+    ) -> Result<(), VirtualListError> {
+        let start = chunk_idx * self.chunk_size;
+        self.call_chunk_callback(loaded, (chunk_idx, start, start + len))
+    }
+
+    fn call_chunk_callback(
+        &self,
+        loaded: bool,
+        (chunk_idx, start, end): (usize, usize, usize),
+    ) -> Result<(), VirtualListError> {
+        let callback = if loaded {
+            &self.on_chunk_loaded
+        } else {
+            &self.on_chunk_unloaded
+        };
+        let Some(callback) = callback else {
+            return Ok(());
+        };
+        callback
+            .call3(
+                &JsValue::NULL,
+                &JsValue::from(chunk_idx as f64),
+                &JsValue::from(start as f64),
+                &JsValue::from(end as f64),
+            )
+            .map(|_| ())
+            .map_err(VirtualListError::Callback)
+    }
+
+    // `(chunk index, start index, end index)` of the loaded chunks `keep`
+    // selects, for reporting a bulk change to the `loaded` or unloaded
+    // callback. Empty when that callback is unset.
+    fn loaded_chunk_spans(
+        &self,
+        loaded: bool,
+        keep: impl Fn(usize) -> bool,
+    ) -> Vec<(usize, usize, usize)> {
+        let callback = if loaded {
+            &self.on_chunk_loaded
+        } else {
+            &self.on_chunk_unloaded
+        };
+        if callback.is_none() {
+            return Vec::new();
+        }
+        self.chunks
+            .iter()
+            .filter(|&(&chunk_idx, _)| keep(chunk_idx))
+            .map(|(&chunk_idx, chunk)| {
+                let start = chunk_idx * self.chunk_size;
+                (chunk_idx, start, start + chunk.len())
+            })
+            .collect()
+    }
+
+    // Reports every span, stopping at the first callback that throws.
+    fn notify_chunk_spans(
+        &self,
+        loaded: bool,
+        spans: Vec<(usize, usize, usize)>,
+    ) -> Result<(), VirtualListError> {
+        for span in spans {
+            self.call_chunk_callback(loaded, span)?;
+        }
+        Ok(())
+    }
+
+    // Materializes a chunk from its estimates without evicting or tracking
+    // access; a no-op if it is already loaded.
+    fn create_chunk(&mut self, chunk_idx: usize) -> Result<(), VirtualListError> {
+        if self.chunks.contains_key(&chunk_idx) {
+            return Ok(());
+        }
+        let chunk_start = chunk_idx * self.chunk_size;
+        let sizes = (chunk_start..chunk_start + self.items_in_chunk(chunk_idx))
+            .map(|i| self.estimated_item_size(i))
+            .collect();
+        let chunk = Chunk::new(sizes, self.config.run_length_chunks)
+            .map_err(|e| convert_error(VirtualListErrorKind::ChunkCreationError, &e))?;
+        self.chunks.insert(chunk_idx, chunk);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn update_item_size(
+        &mut self,
+        index: usize,
+        new_size: f64,
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        self.set_item_size(index, new_size)
+    }
+
+    // `update_item_size` without the generation bump.
+    fn set_item_size(&mut self, index: usize, new_size: f64) -> Result<(), VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        if !is_valid_size(new_size) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size: {}", new_size),
+            ));
+        }
+        let new_size = self.clamp_item_size(new_size);
+        let chunk_idx = index / self.chunk_size;
+        let item_idx = index % self.chunk_size;
+        let chunk = self.get_or_create_chunk(chunk_idx)?;
+        let diff = chunk
+            .update_size(item_idx, new_size)
+            .map_err(|e| convert_error(VirtualListErrorKind::UpdateError, &e))?;
+        self.update_cumulative_sizes(chunk_idx, diff)
+            .map_err(|e| convert_error(VirtualListErrorKind::CumulativeUpdateError, &e))?;
+        self.record_measurements(1, new_size);
+        Ok(())
+    }
+
+    /// `update_item_size` plus scroll anchoring: if item `index` starts above
+    /// `current_scroll`, returns `current_scroll` shifted by however far the
+    /// content after the item moved, so the viewport stays put. Otherwise
+    /// returns `current_scroll` unchanged.
+    #[wasm_bindgen]
+    pub fn measure_and_set(
+        &mut self,
+        index: usize,
+        new_size: f64,
+        current_scroll: f64,
+    ) -> Result<f64, VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        let (leading, trailing) = self.screen_edges(index)?;
+        self.update_item_size(index, new_size)?;
+        if leading >= current_scroll {
+            return Ok(current_scroll);
+        }
+        let (_, new_trailing) = self.screen_edges(index)?;
+        Ok(current_scroll + (new_trailing - trailing))
+    }
+
+    /// `update_item_sizes` plus scroll anchoring: returns `current_scroll`
+    /// shifted by however far the item under it moved, so the first visible
+    /// item stays under the same pixel. Like `measure_and_set`, the shift is
+    /// read from the layout before and after, so estimates that change with
+    /// the update (e.g. `use_dynamic_estimate`) are included.
+    #[wasm_bindgen]
+    pub fn batch_update_sizes_anchored(
+        &mut self,
+        indices: &[u32],
+        sizes: &[f64],
+        current_scroll: f64,
+    ) -> Result<f64, VirtualListError> {
+        let anchor = self.scroll_anchor(current_scroll)?;
+        let before = match anchor {
+            Some(anchor) => self.anchor_edge(anchor)?,
+            None => 0.0,
+        };
+        self.update_item_sizes(indices, sizes)?;
+        let Some(anchor) = anchor else {
+            return Ok(current_scroll);
+        };
+        Ok(current_scroll + (self.anchor_edge(anchor)? - before))
+    }
+
+    // The item under `current_scroll` and whether to hold its trailing
+    // rather than its leading edge still: the trailing one when the item
+    // starts above the scroll position, as in `measure_and_set`. `None` when
+    // no content lies above it.
+    fn scroll_anchor(
+        &mut self,
+        current_scroll: f64,
+    ) -> Result<Option<(usize, bool)>, VirtualListError> {
+        let position = current_scroll - self.config.content_offset;
+        if self.total_items == 0 || position.is_nan() || position <= 0.0 {
+            return Ok(None);
+        }
+        let extent = self.scroll_extent();
+        let position = position.min(extent);
+        let list_position = if self.config.flips_scroll() {
+            extent - position
+        } else {
+            position
+        };
+        let (index, _) = self
+            .find_item_at_position(list_position)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        let (leading, _) = self.screen_edges(index)?;
+        Ok(Some((index, leading < current_scroll)))
+    }
+
+    fn anchor_edge(&mut self, (index, trailing): (usize, bool)) -> Result<f64, VirtualListError> {
+        let (leading, trailing_edge) = self.screen_edges(index)?;
+        Ok(if trailing { trailing_edge } else { leading })
+    }
+
+    // The item's leading and trailing edges on screen, in scroll
+    // coordinates. Reverse lists run item offsets from the bottom.
+    fn screen_edges(&mut self, index: usize) -> Result<(f64, f64), VirtualListError> {
+        let start = self.item_offset(index)?;
+        let end = self.item_end_offset(index)?;
+        let (leading, trailing) = if self.config.flips_scroll() {
+            let extent = self.scroll_extent();
+            (extent - end, extent - start)
+        } else {
+            (start, end)
+        };
+        Ok((
+            self.config.content_offset + leading,
+            self.config.content_offset + trailing,
+        ))
+    }
+
+    /// Adjusts item `index` by `delta`, floored at 0 (or `min_item_size`),
+    /// and records the result as a measurement like `update_item_size`.
+    #[wasm_bindgen]
+    pub fn update_item_size_relative(
+        &mut self,
+        index: usize,
+        delta: f64,
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        if !delta.is_finite() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size delta: {}", delta),
+            ));
+        }
+        let current = self.item_size(index)?;
+        self.set_item_size(index, (current + delta).max(0.0))
+    }
+
+    // Raises valid sizes to `min_item_size`; invalid ones are passed through
+    // so the chunk still rejects them.
+    fn clamp_item_size(&self, size: f64) -> f64 {
+        if size >= 0.0 {
+            size.max(self.config.min_item_size)
+        } else {
+            size
+        }
+    }
+
+    fn record_measurements(&mut self, count: u64, sum: f64) {
+        self.measured_count += count;
+        self.measured_sum += sum;
+        // The measured chunks are already up to date; the others only follow
+        // the mean once it has drifted far enough to matter
+        if self.config.use_dynamic_estimate
+            && (self.dynamic_estimated_size() - self.dynamic_estimate).abs()
+                > DYNAMIC_ESTIMATE_TOLERANCE * self.dynamic_estimate
+        {
+            self.rebuild_cumulative_sizes();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn dynamic_estimated_size(&self) -> f64 {
+        if self.measured_count == 0 {
+            self.estimated_size
+        } else {
+            self.measured_sum / self.measured_count as f64
+        }
+    }
+
+    fn update_cumulative_sizes(&mut self, from_chunk: usize, diff: f64) -> Result<(), String> {
+        self.chunk_offsets.add(from_chunk, diff);
+        self.total_size += diff;
+        Ok(())
+    }
+
+    /// Items to render for the viewport, including buffer and overscan. With
+    /// `boundary_hysteresis` set, the previous range is returned until the
+    /// scroll moves far enough past the boundary that changed it.
+    #[wasm_bindgen]
+    pub fn get_visible_range(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<VisibleRange, VirtualListError> {
+        self.generation_seen.set(true);
+        if let Some(cache) = &self.range_cache
+            && cache.generation == self.generation
+            && (cache.scroll - scroll_position).abs() <= RANGE_CACHE_EPSILON
+            && (cache.viewport - viewport_size).abs() <= RANGE_CACHE_EPSILON
+        {
+            return Ok(cache.range.clone());
+        }
+        let mut range =
+            self.get_visible_range_with_velocity(scroll_position, viewport_size, 0.0)?;
+        let mut anchor = scroll_position;
+        let hysteresis = self.config.boundary_hysteresis;
+        if hysteresis > 0.0
+            && let Some(cache) = self.range_cache.clone()
+            && cache.generation == self.generation
+            && (cache.viewport - viewport_size).abs() <= RANGE_CACHE_EPSILON
+            && (cache.range.start, cache.range.end) != (range.start, range.end)
+        {
+            // Keep the held range unless it also changes with the scroll
+            // pulled back toward its anchor by the hysteresis
+            let pulled_back = if scroll_position > cache.anchor {
+                (scroll_position - hysteresis).max(cache.anchor)
+            } else {
+                (scroll_position + hysteresis).min(cache.anchor)
+            };
+            let held = self.get_visible_range_with_velocity(pulled_back, viewport_size, 0.0)?;
+            if (held.start, held.end) == (cache.range.start, cache.range.end) {
+                range = cache.range;
+                anchor = cache.anchor;
+            }
+        }
+        self.generation_seen.set(true);
+        self.range_cache = Some(RangeCache {
+            scroll: scroll_position,
+            viewport: viewport_size,
+            generation: self.generation,
+            range: range.clone(),
+            anchor,
+        });
+        Ok(range)
+    }
+
+    /// Like `get_visible_range`, but shifts overscan toward the scroll
+    /// direction: positive `velocity` (toward larger offsets) moves overscan
+    /// items from before the viewport to after it, negative the reverse. The
+    /// shift grows with `|velocity| / viewport_size` and saturates once the
+    /// list moves a full viewport per call, so the total stays
+    /// `2 * (buffer_size + overscan_items)`.
+    #[wasm_bindgen]
+    pub fn get_visible_range_with_velocity(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        velocity: f64,
+    ) -> Result<VisibleRange, VirtualListError> {
+        self.resolve_visible_range(scroll_position, viewport_size, Some(velocity))
+    }
+
+    /// The items actually on screen, without `buffer_size` or
+    /// `overscan_items`. An item that only touches the viewport's trailing
+    /// edge is not included.
+    #[wasm_bindgen]
+    pub fn get_visible_range_exact(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<VisibleRange, VirtualListError> {
+        self.resolve_visible_range(scroll_position, viewport_size, None)
+    }
+
+    // Resolves the viewport's edge items, then pads them by buffer and
+    // velocity-biased overscan, or not at all when `velocity` is `None`.
+    fn resolve_visible_range(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        velocity: Option<f64>,
+    ) -> Result<VisibleRange, VirtualListError> {
+        if self.viewport_covers_list(scroll_position, viewport_size)? {
+            let (start, end) = self.skip_zero_sized_edge_items(0, self.total_items - 1)?;
+            return self.build_visible_range(start, end + 1, start, end);
+        }
+        let (scroll_position, end_position, start_idx, mut end_idx) =
+            self.viewport_edge_items(scroll_position, viewport_size)?;
+        if velocity.is_none() {
+            end_idx = self.exact_end_item(start_idx, end_idx, end_position)?;
+        }
+        let (start_idx, end_idx) = self.skip_zero_sized_edge_items(start_idx, end_idx)?;
+        let start_edge = self.item_offset(start_idx)?;
+        let end_edge = self.item_end_offset(end_idx)?;
+        let (first_fully_visible, last_fully_visible) = Self::fully_visible_bounds(
+            (start_idx, start_edge),
+            (end_idx, end_edge),
+            (scroll_position, end_position),
+        );
+        let (start, end) = match velocity {
+            Some(velocity) => {
+                // Scrolling down a flipped list moves toward lower indices
+                let velocity = if self.config.flips_scroll() {
+                    -velocity
+                } else {
+                    velocity
+                };
+                self.render_bounds(
+                    start_idx,
+                    end_idx,
+                    (scroll_position, end_position),
+                    velocity / viewport_size,
+                )
+            }
+            None => (start_idx, end_idx + 1),
+        };
+        self.build_visible_range(start, end, first_fully_visible, last_fully_visible)
+    }
+
+    // Applies `skip_zero_sized_start` to the viewport's edge items
+    // `start_idx..=end_idx`.
+    fn skip_zero_sized_edge_items(
+        &mut self,
+        start_idx: usize,
+        end_idx: usize,
+    ) -> Result<(usize, usize), VirtualListError> {
+        if !self.config.skip_zero_sized_start {
+            return Ok((start_idx, end_idx));
+        }
+        let (start, end) = self.trim_zero_sized_edges(start_idx, end_idx + 1)?;
+        Ok((start, end - 1))
+    }
+
+    // Fills in a `VisibleRange` for the render range `[start, end)`.
+    fn build_visible_range(
+        &mut self,
+        start: usize,
+        end: usize,
+        first_fully_visible: usize,
+        last_fully_visible: usize,
+    ) -> Result<VisibleRange, VirtualListError> {
+        let start_offset = self.item_offset(start)?;
+        let end_offset = self.item_end_offset(end - 1)?;
+        Ok(VisibleRange {
+            start,
+            end,
+            start_offset,
+            end_offset,
+            first_fully_visible,
+            last_fully_visible,
+            sections: self.sections_in(start, end),
+            pinned: self.pinned.iter().map(|&index| index as u32).collect(),
+        })
+    }
+
+    /// Calls `callback(index, offset, size)` for every item of
+    /// `get_visible_range`, in order, skipping pinned items. An exception
+    /// thrown by the callback stops the iteration and is returned as the
+    /// error.
+    #[wasm_bindgen]
+    pub fn for_each_visible(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        callback: &Function,
+    ) -> Result<(), VirtualListError> {
+        let range = self.get_visible_range(scroll_position, viewport_size)?;
+        for index in range.start..range.end {
+            if self.pinned.binary_search(&index).is_ok() {
+                continue;
+            }
+            let offset = self.item_offset(index)?;
+            let size = self.item_size(index)?;
+            callback.call3(
+                &JsValue::NULL,
+                &JsValue::from(index as f64),
+                &JsValue::from(offset),
+                &JsValue::from(size),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Indices in the render range at `new_scroll` that were not in it at
+    /// `old_scroll`, in order, for rendering only what scrolled in. Pinned
+    /// items are skipped as in `for_each_visible`.
+    #[wasm_bindgen]
+    pub fn newly_visible(
+        &mut self,
+        old_scroll: f64,
+        new_scroll: f64,
+        viewport_size: f64,
+    ) -> Result<Vec<u32>, VirtualListError> {
+        Ok(self
+            .visible_range_delta(old_scroll, new_scroll, viewport_size)?
+            .added)
+    }
+
+    /// Render range changes between two scroll positions: the indices that
+    /// entered and left it, for mounting and unmounting only those nodes.
+    /// Pinned items are skipped as in `for_each_visible`.
+    #[wasm_bindgen]
+    pub fn visible_range_delta(
+        &mut self,
+        old_scroll: f64,
+        new_scroll: f64,
+        viewport_size: f64,
+    ) -> Result<RangeDelta, VirtualListError> {
+        let old = self.get_visible_range_with_velocity(old_scroll, viewport_size, 0.0)?;
+        let new = self.get_visible_range_with_velocity(new_scroll, viewport_size, 0.0)?;
+        let difference = |from: &VisibleRange, without: &VisibleRange| -> Vec<u32> {
+            (from.start..from.end)
+                .filter(|index| !(without.start..without.end).contains(index))
+                .filter(|index| self.pinned.binary_search(index).is_err())
+                .map(|index| index as u32)
+                .collect()
+        };
+        Ok(RangeDelta {
+            added: difference(&new, &old),
+            removed: difference(&old, &new),
+        })
+    }
+
+    /// The items on screen plus `overscan` items on each side, with their
+    /// offsets and sizes. `overscan` replaces the configured buffer and
+    /// overscan; pinned items are skipped as in `for_each_visible`.
+    #[wasm_bindgen]
+    pub fn compute_visible_range(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        overscan: usize,
+    ) -> Result<Vec<VisibleItem>, VirtualListError> {
+        let (start, end) = if self.viewport_covers_list(scroll_position, viewport_size)? {
+            (0, self.total_items)
+        } else {
+            let (_, end_position, start_idx, end_idx) =
+                self.viewport_edge_items(scroll_position, viewport_size)?;
+            let visible_end = self.exact_end_item(start_idx, end_idx, end_position)? + 1;
+            (
+                start_idx.saturating_sub(overscan),
+                visible_end.saturating_add(overscan).min(self.total_items),
+            )
+        };
+        let mut items = Vec::with_capacity(end - start);
+        for index in start..end {
+            if self.pinned.binary_search(&index).is_ok() {
+                continue;
+            }
+            items.push(VisibleItem {
+                index,
+                offset: self.item_offset(index)?,
+                size: self.item_size(index)?,
+            });
+        }
+        Ok(items)
+    }
+
+    /// The on-screen range and the render range (with buffer and overscan)
+    /// from a single lookup.
+    #[wasm_bindgen]
+    pub fn get_visible_range_detailed(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<VisibleRangeDetailed, VirtualListError> {
+        if self.viewport_covers_list(scroll_position, viewport_size)? {
+            return Ok(VisibleRangeDetailed {
+                visible_start: 0,
+                visible_end: self.total_items,
+                render_start: 0,
+                render_end: self.total_items,
+                render_start_offset: self.item_offset(0)?,
+                render_end_offset: self.item_end_offset(self.total_items - 1)?,
+            });
+        }
+        let (scroll_position, end_position, start_idx, end_idx) =
+            self.viewport_edge_items(scroll_position, viewport_size)?;
+        let visible_end = self.exact_end_item(start_idx, end_idx, end_position)? + 1;
+        let (render_start, render_end) =
+            self.render_bounds(start_idx, end_idx, (scroll_position, end_position), 0.0);
+        let render_start_offset = self.item_offset(render_start)?;
+        let render_end_offset = self.item_end_offset(render_end - 1)?;
+        Ok(VisibleRangeDetailed {
+            visible_start: start_idx,
+            visible_end,
+            render_start,
+            render_end,
+            render_start_offset,
+            render_end_offset,
+        })
+    }
+
+    // The clamped viewport window and the items at its two edges.
+    fn viewport_edge_items(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<(f64, f64, usize, usize), VirtualListError> {
+        let (scroll_position, end_position) =
+            self.visible_window(scroll_position, viewport_size)?;
+        let (start_idx, _) = self
+            .find_item_at_position(scroll_position)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        let (end_idx, _) = self
+            .find_item_at_position(end_position)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        Ok((scroll_position, end_position, start_idx, end_idx))
+    }
+
+    // Drops the trailing edge item when it only touches the viewport's end.
+    fn exact_end_item(
+        &mut self,
+        start_idx: usize,
+        end_idx: usize,
+        end_position: f64,
+    ) -> Result<usize, VirtualListError> {
+        if end_idx > start_idx && self.item_offset(end_idx)? >= end_position {
+            Ok(end_idx - 1)
+        } else {
+            Ok(end_idx)
+        }
+    }
+
+    /// Approximate `get_visible_range` that never loads chunks: positions in
+    /// unloaded chunks are estimated by spreading the chunk's estimated total
+    /// evenly over its items. Suited to fast scroll frames where precision
+    /// within a chunk doesn't matter.
+    #[wasm_bindgen]
+    pub fn get_visible_range_cached(
+        &self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<VisibleRange, VirtualListError> {
+        let (first_fully_visible, last_fully_visible, start, end) =
+            if self.viewport_covers_list(scroll_position, viewport_size)? {
+                (0, self.total_items - 1, 0, self.total_items)
+            } else {
+                let (scroll_position, end_position) =
+                    self.visible_window(scroll_position, viewport_size)?;
+                let (start_idx, _) = self.peek_item_at_position(scroll_position);
+                let (end_idx, _) = self.peek_item_at_position(end_position);
+                let (first_fully_visible, last_fully_visible) = Self::fully_visible_bounds(
+                    (start_idx, self.peek_item_offset(start_idx)),
+                    (end_idx, self.peek_item_end_offset(end_idx)),
+                    (scroll_position, end_position),
+                );
+                let (start, end) =
+                    self.render_bounds(start_idx, end_idx, (scroll_position, end_position), 0.0);
+                (first_fully_visible, last_fully_visible, start, end)
+            };
+        Ok(VisibleRange {
+            start,
+            end,
+            start_offset: self.peek_item_offset(start),
+            end_offset: self.peek_item_end_offset(end - 1),
+            first_fully_visible,
+            last_fully_visible,
+            sections: self.sections_in(start, end),
+            pinned: self.pinned.iter().map(|&index| index as u32).collect(),
+        })
+    }
+
+    // Drops zero-sized items from both ends of `[start, end)`, keeping at
+    // least one item.
+    fn trim_zero_sized_edges(
+        &mut self,
+        mut start: usize,
+        mut end: usize,
+    ) -> Result<(usize, usize), VirtualListError> {
+        while end - start > 1 && self.item_size(start)? == 0.0 {
+            start += 1;
+        }
+        while end - start > 1 && self.item_size(end - 1)? == 0.0 {
+            end -= 1;
+        }
+        Ok((start, end))
+    }
+
+    // Narrows the items at the viewport edges to those fully inside it, given
+    // the leading edge of the first and the trailing edge of the last.
+    fn fully_visible_bounds(
+        (start_idx, start_edge): (usize, f64),
+        (end_idx, end_edge): (usize, f64),
+        (scroll_position, end_position): (f64, f64),
+    ) -> (usize, usize) {
+        let first = if start_edge < scroll_position {
+            start_idx + 1
+        } else {
+            start_idx
+        };
+        let last = if end_edge > end_position {
+            end_idx.saturating_sub(1)
+        } else {
+            end_idx
+        };
+        if first > last {
+            (start_idx, start_idx)
+        } else {
+            (first, last)
+        }
+    }
+
+    // Validates the viewport and reports whether it is at least as tall as
+    // the list. Such a viewport shows all of it at any scroll position, so
+    // the range queries skip the edge lookups and padding.
+    fn viewport_covers_list(
+        &self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<bool, VirtualListError> {
+        self.visible_window(scroll_position, viewport_size)?;
+        Ok(viewport_size >= self.scroll_extent())
+    }
+
+    // Validates the viewport and clamps it to the scroll extent, returning
+    // its leading and trailing positions. Scroll positions past the last full
+    // screen are clamped to it, so the final items stay in view.
+    fn visible_window(
+        &self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<(f64, f64), VirtualListError> {
+        if viewport_size <= 0.0 {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidViewport,
+                "Viewport size must be positive",
+            ));
+        }
+        if self.total_items == 0 {
+            return Err(convert_error(
+                VirtualListErrorKind::EmptyList,
+                "List is empty",
+            ));
+        }
+        let scroll_extent = self.scroll_extent();
+        let scroll_position = (scroll_position - self.config.content_offset)
+            .max(0.0)
+            .min(self.max_list_scroll(viewport_size));
+        if self.config.flips_scroll() {
+            let end_position = scroll_extent - scroll_position;
+            return Ok(((end_position - viewport_size).max(0.0), end_position));
+        }
+        let end_position = (scroll_position + viewport_size).min(scroll_extent);
+        Ok((scroll_position, end_position))
+    }
+
+    /// Scroll position that shows the end of the list, or item 0 when
+    /// exactly one of `reverse` and `invert_axis` is set.
+    #[wasm_bindgen]
+    pub fn scroll_to_bottom(&self, viewport_size: f64) -> f64 {
+        self.max_scroll_offset(viewport_size)
+    }
+
+    /// Largest valid scroll position for `viewport_size`, including gaps,
+    /// section headers, padding and `content_offset`.
+    #[wasm_bindgen]
+    pub fn max_scroll_offset(&self, viewport_size: f64) -> f64 {
+        self.config.content_offset + self.max_list_scroll(viewport_size)
+    }
+
+    /// Clamps `scroll_position` to `[0, max_scroll_offset(viewport_size)]`.
+    #[wasm_bindgen]
+    pub fn clamp_scroll(&self, scroll_position: f64, viewport_size: f64) -> f64 {
+        scroll_position
+            .max(0.0)
+            .min(self.max_scroll_offset(viewport_size))
+    }
+
+    // `max_scroll_offset` relative to the start of the list
+    fn max_list_scroll(&self, viewport_size: f64) -> f64 {
+        (self.scroll_extent() - viewport_size).max(0.0)
+    }
+
+    // Extends the items hit by the viewport edges by buffer and overscan,
+    // shifting up to all of the overscan toward the side `bias` points to.
+    // `bias` is the scroll velocity in viewports per call; `window` is the
+    // viewport's leading and trailing position.
+    fn render_bounds(
+        &self,
+        start_idx: usize,
+        end_idx: usize,
+        window: (f64, f64),
+        bias: f64,
+    ) -> (usize, usize) {
+        let buffer = self.config.buffer_size;
+        let bias = if bias.is_nan() {
+            0.0
+        } else {
+            bias.clamp(-1.0, 1.0)
+        };
+        let (first, last) = match self.config.overscan_px {
+            Some(pixels) => {
+                let shift = pixels * bias.abs();
+                let (before, after) = if bias > 0.0 {
+                    (pixels - shift, pixels + shift)
+                } else {
+                    (pixels + shift, pixels - shift)
+                };
+                // Estimated within unloaded chunks, like `get_visible_range_cached`
+                let (first, _) = self.peek_item_at_position(window.0 - before);
+                let (last, _) = self.peek_item_at_position(window.1 + after);
+                (first.min(start_idx), last.max(end_idx))
+            }
+            None => {
+                let overscan = self.config.overscan_items;
+                // Saturating throughout: buffer and overscan are caller-controlled
+                let shift = ((overscan as f64 * bias.abs()).round() as usize).min(overscan);
+                let (before, after) = if bias > 0.0 {
+                    (overscan - shift, overscan.saturating_add(shift))
+                } else {
+                    (overscan.saturating_add(shift), overscan - shift)
+                };
+                (
+                    start_idx.saturating_sub(before),
+                    end_idx.saturating_add(after),
+                )
+            }
+        };
+        let start = first.saturating_sub(buffer);
+        let end = last
+            .saturating_add(buffer)
+            .saturating_add(1)
+            .min(self.total_items);
+        (start, end)
+    }
+
+    // Returns the item at `position` and the position's offset into it. The
+    // end of the list belongs to the last item: at exactly `total_size` the
+    // result is the last item with an offset of its full size.
+    fn find_item_at_position(&mut self, position: f64) -> Result<(usize, f64), String> {
+        if self.total_items == 0 {
+            return Ok((0, 0.0));
+        }
+        let chunk_idx = self.chunk_at_position(position + self.config.position_epsilon);
+        self.get_or_create_chunk(chunk_idx)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(self.peek_item_at_position(position))
+    }
+
+    // Positions inside the padding resolve to the first or last item
+    fn content_position(&self, position: f64) -> f64 {
+        (position - self.config.padding_start)
+            .max(0.0)
+            .min(self.content_size())
+    }
+
+    fn chunk_at_position(&self, position: f64) -> usize {
+        let position = self.content_position(position);
+        partition_point_by(self.num_chunks() - 1, |c| {
+            self.chunk_offset(c + 1) <= position
+        })
+    }
+
+    // Like `find_item_at_position`, but never loads a chunk: items in
+    // unloaded chunks are located as if the chunk's estimated total were
+    // spread evenly across them.
+    fn peek_item_at_position(&self, position: f64) -> (usize, f64) {
+        let epsilon = self.config.position_epsilon;
+        if epsilon == 0.0 {
+            return self.locate_item(position);
+        }
+        // An edge in `(position, position + epsilon]` counts as reached
+        let (index, offset) = self.locate_item(position + epsilon);
+        if offset < epsilon {
+            (index, 0.0)
+        } else {
+            (index, offset - epsilon)
+        }
+    }
+
+    // `peek_item_at_position` without the `position_epsilon` snapping.
+    fn locate_item(&self, position: f64) -> (usize, f64) {
+        if self.total_items == 0 {
+            return (0, 0.0);
+        }
+        let chunk_idx = self.chunk_at_position(position);
+        let position_in_chunk =
+            (self.content_position(position) - self.chunk_offset(chunk_idx)).max(0.0);
+        let chunk_start = chunk_idx * self.chunk_size;
+        let base_extra = self.extra_before(chunk_start);
+        let extra = |i| self.extra_before(chunk_start + i) - base_extra;
+        let (item_idx, offset) = match self.chunks.get(&chunk_idx) {
+            Some(chunk) => chunk
+                .find_item_at_position(position_in_chunk, extra)
+                .unwrap_or((0, 0.0)),
+            None => {
+                let items_in_chunk = self.items_in_chunk(chunk_idx);
+                let average = self.chunk_total(chunk_idx) / items_in_chunk as f64;
+                find_in_prefix_sums(
+                    items_in_chunk,
+                    |i| average * i as f64,
+                    extra,
+                    position_in_chunk,
+                )
+            }
+        };
+        ((chunk_start + item_idx).min(self.total_items - 1), offset)
+    }
+
+    fn chunk_total(&self, chunk_idx: usize) -> f64 {
+        self.chunk_offsets.prefix(chunk_idx + 1) - self.chunk_offsets.prefix(chunk_idx)
+    }
+
+    // Non-item space laid out before the leading edge of item `index`,
+    // less the pinned items before it, which take no scroll space.
+    fn extra_before(&self, index: usize) -> f64 {
+        self.config.gap * index as f64 + self.section_header_sums[self.sections_through(index)]
+            - self.pinned_size_before(index)
+    }
+
+    fn pinned_size_before(&self, index: usize) -> f64 {
+        self.pinned
+            .iter()
+            .take_while(|&&pinned| pinned < index)
+            .map(|&pinned| self.peek_item_size(pinned))
+            .sum()
+    }
+
+    fn peek_item_size(&self, index: usize) -> f64 {
+        self.peek_sizes_before(index + 1) - self.peek_sizes_before(index)
+    }
+
+    // Number of section headers sitting at or before item `index`.
+    fn sections_through(&self, index: usize) -> usize {
+        self.sections
+            .partition_point(|&(section_start, _)| section_start <= index)
+    }
+
+    fn replace_sections(&mut self, sections: Vec<(usize, f64)>) {
+        self.section_header_sums = std::iter::once(0.0)
+            .chain(sections.iter().scan(0.0, |sum, &(_, size)| {
+                *sum += size;
+                Some(*sum)
+            }))
+            .collect();
+        self.sections = sections;
+    }
+
+    // Ordinals of the sections whose headers sit before an item in `[start, end)`.
+    fn sections_in(&self, start: usize, end: usize) -> Vec<u32> {
+        let first = self.sections.partition_point(|&(index, _)| index < start);
+        let last = self.sections.partition_point(|&(index, _)| index < end);
+        (first as u32..last as u32).collect()
+    }
+
+    // Leading edge of the first item in `chunk_idx`.
+    fn chunk_offset(&self, chunk_idx: usize) -> f64 {
+        self.chunk_offsets.prefix(chunk_idx) + self.extra_before(chunk_idx * self.chunk_size)
+    }
+
+    // Item sizes plus the space laid out between them, excluding padding.
+    fn content_size(&self) -> f64 {
+        if self.total_items == 0 {
+            return 0.0;
+        }
+        let last = self.total_items - 1;
+        let last_pinned = if self.pinned.last() == Some(&last) {
+            self.peek_item_size(last)
+        } else {
+            0.0
+        };
+        self.total_size + self.extra_before(last) - last_pinned
+    }
+
+    fn scroll_extent(&self) -> f64 {
+        self.config.padding_start + self.content_size() + self.config.padding_end
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_size(&self) -> f64 {
+        self.scroll_extent()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_items(&self) -> usize {
+        self.total_items
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// A copy of the effective config, e.g. to modify and pass to
+    /// `set_config`.
+    #[wasm_bindgen(getter)]
+    pub fn config(&self) -> VirtualListConfig {
+        self.config.clone()
+    }
+
+    /// Replaces the whole config at once. Measured sizes are kept; if the
+    /// new `max_loaded_chunks` is lower than the number of loaded chunks, the
+    /// least recently used ones are evicted right away.
+    #[wasm_bindgen]
+    pub fn set_config(&mut self, config: VirtualListConfig) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        self.config = config;
+        // Estimates for unloaded chunks may depend on `use_dynamic_estimate`
+        self.rebuild_cumulative_sizes();
+        if let Some(max) = self.config.max_loaded_chunks
+            && self.chunk_access.len() > max
+        {
+            self.evict_all_but(max)?;
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Switches the scroll axis in place, e.g. at a responsive breakpoint.
+    /// Sizes are lengths along the scroll axis and carry over as-is; the
+    /// generation is bumped so callers re-render.
+    #[wasm_bindgen]
+    pub fn set_orientation(&mut self, orientation: Orientation) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        self.orientation = orientation;
+        Ok(())
+    }
+
+    /// `"y"` for vertical lists, `"x"` for horizontal ones.
+    #[wasm_bindgen]
+    pub fn axis_label(&self) -> String {
+        self.orientation.axis().to_string()
+    }
+
+    /// `"height"` for vertical lists, `"width"` for horizontal ones.
+    #[wasm_bindgen]
+    pub fn size_label(&self) -> String {
+        self.orientation.dimension().to_string()
+    }
+
+    /// The configured estimate for unmeasured items. See
+    /// `dynamic_estimated_size` for the running mean of measurements.
+    #[wasm_bindgen(getter)]
+    pub fn estimated_size(&self) -> f64 {
+        self.estimated_size
+    }
+
+    /// Whether `position` lies within the list's scroll extent, i.e. whether
+    /// `item_at_position` would resolve it without clamping.
+    #[wasm_bindgen]
+    pub fn contains_position(&self, position: f64) -> bool {
+        self.total_items > 0 && position >= 0.0 && position <= self.scroll_extent()
+    }
+
+    #[wasm_bindgen]
+    pub fn item_at_position(&mut self, position: f64) -> Result<ItemHit, VirtualListError> {
+        if position.is_nan() {
+            return Err(convert_error(
+                VirtualListErrorKind::PositionError,
+                &format!("Invalid position: {}", position),
+            ));
+        }
+        if self.total_items == 0 {
+            return Err(convert_error(
+                VirtualListErrorKind::EmptyList,
+                "List is empty",
+            ));
+        }
+        let position = position.max(0.0).min(self.scroll_extent());
+        let (index, offset) = self
+            .find_item_at_position(position)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        Ok(ItemHit { index, offset })
+    }
+
+    /// The item index at each of `positions`, clamped to the list like
+    /// `item_at_position`. Queries are resolved in position order, so each
+    /// chunk they touch is loaded at most once.
+    #[wasm_bindgen]
+    pub fn items_at_positions(&mut self, positions: &[f64]) -> Result<Vec<u32>, VirtualListError> {
+        if let Some(&position) = positions.iter().find(|position| position.is_nan()) {
+            return Err(convert_error(
+                VirtualListErrorKind::PositionError,
+                &format!("Invalid position: {}", position),
+            ));
+        }
+        if self.total_items == 0 {
+            return Err(convert_error(
+                VirtualListErrorKind::EmptyList,
+                "List is empty",
+            ));
+        }
+        let extent = self.scroll_extent();
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        order.sort_unstable_by(|&a, &b| positions[a].total_cmp(&positions[b]));
+        let mut indices = vec![0; positions.len()];
+        for query in order {
+            let position = positions[query].max(0.0).min(extent);
+            let (index, _) = self
+                .find_item_at_position(position)
+                .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+            indices[query] = index as u32;
+        }
+        Ok(indices)
+    }
+
+    /// Number of items overlapping `[start_offset, end_offset]`, without
+    /// building the index list. Only the chunks at the two ends are loaded;
+    /// an item that merely starts at `end_offset` is not counted.
+    #[wasm_bindgen]
+    pub fn item_count_in_range(
+        &mut self,
+        start_offset: f64,
+        end_offset: f64,
+    ) -> Result<usize, VirtualListError> {
+        if start_offset.is_nan() || end_offset.is_nan() {
+            return Err(convert_error(
+                VirtualListErrorKind::PositionError,
+                &format!("Invalid range {}..{}", start_offset, end_offset),
+            ));
+        }
+        if start_offset > end_offset {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidRange,
+                &format!("Invalid range {}..{}", start_offset, end_offset),
+            ));
+        }
+        if self.total_items == 0 {
+            return Ok(0);
+        }
+        let extent = self.scroll_extent();
+        let start_offset = start_offset.max(0.0).min(extent);
+        let end_offset = end_offset.max(0.0).min(extent);
+        let (start_idx, _) = self
+            .find_item_at_position(start_offset)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        let (end_idx, _) = self
+            .find_item_at_position(end_offset)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        let end_idx = self.exact_end_item(start_idx, end_idx, end_offset)?;
+        Ok(end_idx - start_idx + 1)
+    }
+
+    /// The item start nearest to `position`: either the start of the item
+    /// containing it or of the next item, preferring the former on a tie.
+    /// For snapping a scroll offset to item boundaries.
+    #[wasm_bindgen]
+    pub fn nearest_item_boundary(
+        &mut self,
+        position: f64,
+    ) -> Result<ItemBoundary, VirtualListError> {
+        let hit = self.item_at_position(position)?;
+        let start = self.item_offset(hit.index)?;
+        let next = hit.index + 1;
+        if next < self.total_items {
+            let next_start = self.item_offset(next)?;
+            if (next_start - position).abs() < (position - start).abs() {
+                return Ok(ItemBoundary {
+                    index: next,
+                    offset: next_start,
+                });
+            }
+        }
+        Ok(ItemBoundary {
+            index: hit.index,
+            offset: start,
+        })
+    }
+
+    /// Leading edge of item `index`, or `IndexOutOfBounds` past the end.
+    #[wasm_bindgen]
+    pub fn checked_get_position(&mut self, index: usize) -> Result<f64, VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} out of bounds", index),
+            ));
+        }
+        self.item_offset(index)
+    }
+
+    /// Leading edge, size and trailing edge of item `index` in one call, or
+    /// `IndexOutOfBounds` past the end.
+    #[wasm_bindgen]
+    pub fn get_item_bounds(&mut self, index: usize) -> Result<ItemBounds, VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} out of bounds", index),
+            ));
+        }
+        let size = self.item_size(index)?;
+        let start = self.peek_item_offset(index);
+        Ok(ItemBounds {
+            start,
+            size,
+            end: start + size,
+        })
+    }
+
+    /// Like `checked_get_position`, but clamps `index` to the last item. An
+    /// empty list returns `padding_start`.
+    #[wasm_bindgen]
+    pub fn get_position(&mut self, index: usize) -> f64 {
+        let index = index.min(self.total_items.saturating_sub(1));
+        self.item_offset(index)
+            .unwrap_or_else(|_| self.peek_item_offset(index))
+    }
+
+    /// The inverse of `get_position`: the smallest index whose leading edge
+    /// is at or past `target`, or `total_items` if none is. Among zero-sized
+    /// items sharing an edge, the first one is returned.
+    #[wasm_bindgen]
+    pub fn find_smallest_i_where_prefix_sum_ge(&mut self, target: f64) -> usize {
+        if self.total_items == 0 || target.is_nan() {
+            return 0;
+        }
+        let (hit, _) = self
+            .find_item_at_position(target)
+            .unwrap_or_else(|_| self.peek_item_at_position(target));
+        let upper = if self.get_position(hit) >= target {
+            hit
+        } else {
+            hit + 1
+        };
+        // Leading edges never decrease, so this is a lower-bound search: the
+        // lookup skips zero-sized items ending exactly at `target`, so step
+        // back over whole chunks while their first edge is still past it,
+        // then bisect within the one chunk that can hold the first tied edge.
+        let mut chunk_start = upper.min(self.total_items - 1) / self.chunk_size * self.chunk_size;
+        while chunk_start > 0 && self.get_position(chunk_start) >= target {
+            chunk_start -= self.chunk_size;
+        }
+        let end = upper.min(chunk_start + self.chunk_size);
+        chunk_start
+            + partition_point_by(end - chunk_start, |i| {
+                self.get_position(chunk_start + i) < target
+            })
+    }
+
+    fn item_offset(&mut self, index: usize) -> Result<f64, VirtualListError> {
+        self.load_chunk_for_prefix(index)?;
+        Ok(self.peek_item_offset(index))
+    }
+
+    fn item_end_offset(&mut self, index: usize) -> Result<f64, VirtualListError> {
+        self.load_chunk_for_prefix(index + 1)?;
+        Ok(self.peek_item_end_offset(index))
+    }
+
+    fn sizes_before(&mut self, index: usize) -> Result<f64, VirtualListError> {
+        self.load_chunk_for_prefix(index)?;
+        Ok(self.peek_sizes_before(index))
+    }
+
+    // Loads the chunk whose prefix sums `sizes_before(index)` needs, if any.
+    fn load_chunk_for_prefix(&mut self, index: usize) -> Result<(), VirtualListError> {
+        if index < self.total_items && !index.is_multiple_of(self.chunk_size) {
+            self.get_or_create_chunk(index / self.chunk_size)?;
+        }
+        Ok(())
+    }
+
+    fn peek_item_offset(&self, index: usize) -> f64 {
+        if index >= self.total_items {
+            return self.config.padding_start + self.content_size();
+        }
+        self.config.padding_start + self.peek_sizes_before(index) + self.extra_before(index)
+    }
+
+    fn peek_item_end_offset(&self, index: usize) -> f64 {
+        self.config.padding_start + self.peek_sizes_before(index + 1) + self.extra_before(index)
+    }
+
+    // Sum of the sizes of items before `index`. Only the chunk containing
+    // `index` matters; if it is unloaded its total is spread evenly.
+    fn peek_sizes_before(&self, index: usize) -> f64 {
+        if index >= self.total_items {
+            return self.total_size;
+        }
         let chunk_idx = index / self.chunk_size;
         let item_idx = index % self.chunk_size;
-        let chunk = self.get_or_create_chunk(chunk_idx)?;
-        let diff = chunk
-            .update_size(item_idx, new_size)
-            .map_err(|e| convert_error("UpdateError", &e))?;
-        self.update_cumulative_sizes(chunk_idx, diff)
-            .map_err(|e| convert_error("CumulativeUpdateError", &e))?;
+        let chunk_start = self.chunk_offsets.prefix(chunk_idx);
+        if item_idx == 0 {
+            return chunk_start;
+        }
+        match self.chunks.get(&chunk_idx) {
+            Some(chunk) => chunk_start + chunk.prefix(item_idx),
+            None => {
+                let items_in_chunk = self.items_in_chunk(chunk_idx);
+                chunk_start + self.chunk_total(chunk_idx) * item_idx as f64 / items_in_chunk as f64
+            }
+        }
+    }
+
+    /// Scroll position that brings item `index` into view with the given
+    /// alignment, clamped to the scrollable range.
+    #[wasm_bindgen]
+    pub fn scroll_to_item(
+        &mut self,
+        index: usize,
+        alignment: ItemAlignment,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<f64, VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        if viewport_size <= 0.0 {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidViewport,
+                "Viewport size must be positive",
+            ));
+        }
+        let max_scroll = self.max_list_scroll(viewport_size);
+        let scroll_position = scroll_position - self.config.content_offset;
+        let scroll_position = if self.config.flips_scroll() {
+            max_scroll - scroll_position.max(0.0).min(max_scroll)
+        } else {
+            scroll_position
+        };
+        let item_start = self.item_offset(index)?;
+        let item_end = self.item_end_offset(index)?;
+        let target = match alignment {
+            ItemAlignment::Start => item_start,
+            ItemAlignment::Center => (item_start + item_end - viewport_size) / 2.0,
+            ItemAlignment::End => item_end - viewport_size,
+            ItemAlignment::Auto => {
+                if item_start < scroll_position {
+                    item_start
+                } else if item_end > scroll_position + viewport_size {
+                    item_end - viewport_size
+                } else {
+                    scroll_position
+                }
+            }
+        };
+        let target = target.max(0.0).min(max_scroll);
+        let target = if self.config.flips_scroll() {
+            max_scroll - target
+        } else {
+            target
+        };
+        Ok(self.config.content_offset + target)
+    }
+
+    /// Whether any part of item `index` lies within
+    /// `[scroll_position, scroll_position + viewport_size]`.
+    #[wasm_bindgen]
+    pub fn is_item_visible(
+        &mut self,
+        index: usize,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<bool, VirtualListError> {
+        let (item_start, item_end, view_start, view_end) =
+            self.item_in_viewport(index, scroll_position, viewport_size)?;
+        if item_start == item_end {
+            return Ok(item_start >= view_start && item_start <= view_end);
+        }
+        Ok(item_start <= view_end && item_end > view_start)
+    }
+
+    /// Fraction of item `index` inside the viewport, from 0.0 to 1.0.
+    /// Zero-sized items count as fully visible when they sit in the viewport.
+    #[wasm_bindgen]
+    pub fn item_visibility_ratio(
+        &mut self,
+        index: usize,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<f64, VirtualListError> {
+        let (item_start, item_end, view_start, view_end) =
+            self.item_in_viewport(index, scroll_position, viewport_size)?;
+        let size = item_end - item_start;
+        if size == 0.0 {
+            let visible = item_start >= view_start && item_start <= view_end;
+            return Ok(if visible { 1.0 } else { 0.0 });
+        }
+        let overlap = item_end.min(view_end) - item_start.max(view_start);
+        Ok((overlap / size).clamp(0.0, 1.0))
+    }
+
+    // Item `index`'s leading and trailing edges alongside the viewport window.
+    fn item_in_viewport(
+        &mut self,
+        index: usize,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<(f64, f64, f64, f64), VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        let (view_start, view_end) = self.visible_window(scroll_position, viewport_size)?;
+        let item_start = self.item_offset(index)?;
+        let item_end = self.item_end_offset(index)?;
+        Ok((item_start, item_end, view_start, view_end))
+    }
+
+    /// Scroll position `pages` viewports away from `current_scroll` (negative
+    /// pages move back), snapped to the leading edge of the item landed on
+    /// and clamped to the scrollable range. Paging into either end of the
+    /// list lands exactly on it, and items longer than a viewport are not
+    /// snapped back past the current position.
+    #[wasm_bindgen]
+    pub fn scroll_by_pages(
+        &mut self,
+        current_scroll: f64,
+        viewport_size: f64,
+        pages: i32,
+    ) -> Result<f64, VirtualListError> {
+        let (current, _) = self.visible_window(current_scroll, viewport_size)?;
+        let max_scroll = self.max_list_scroll(viewport_size);
+        let pages = if self.config.flips_scroll() {
+            -pages
+        } else {
+            pages
+        };
+        let target = (current + pages as f64 * viewport_size)
+            .max(0.0)
+            .min(max_scroll);
+        let (index, _) = self
+            .find_item_at_position(target)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        let snapped = self.item_offset(index)?.max(0.0).min(max_scroll);
+        let moved_forward = if pages > 0 {
+            snapped > current
+        } else {
+            snapped < current
+        };
+        // The ends of the list are already aligned; elsewhere only snap if it
+        // still moves in the requested direction
+        let at_end = target <= 0.0 || target >= max_scroll;
+        let next = if !at_end && (moved_forward || pages == 0) {
+            snapped
+        } else {
+            target
+        };
+        let next = if self.config.flips_scroll() {
+            max_scroll - next
+        } else {
+            next
+        };
+        Ok(self.config.content_offset + next)
+    }
+
+    /// First item after `from` with a nonzero size, for arrow-key focus
+    /// movement past collapsed rows. Returns the last item if every later
+    /// item is collapsed.
+    #[wasm_bindgen]
+    pub fn next_visible_index(&mut self, from: usize) -> Result<usize, VirtualListError> {
+        if from >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", from),
+            ));
+        }
+        for index in from + 1..self.total_items {
+            if self.item_size(index)? > 0.0 {
+                return Ok(index);
+            }
+        }
+        Ok(self.total_items - 1)
+    }
+
+    /// Last item before `from` with a nonzero size, or item 0 if every
+    /// earlier item is collapsed.
+    #[wasm_bindgen]
+    pub fn prev_visible_index(&mut self, from: usize) -> Result<usize, VirtualListError> {
+        if from >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", from),
+            ));
+        }
+        for index in (0..from).rev() {
+            if self.item_size(index)? > 0.0 {
+                return Ok(index);
+            }
+        }
+        Ok(0)
+    }
+
+    // Size of item `index`, loading its chunk
+    fn item_size(&mut self, index: usize) -> Result<f64, VirtualListError> {
+        let item_idx = index % self.chunk_size;
+        let chunk = self.get_or_create_chunk(index / self.chunk_size)?;
+        Ok(chunk.size(item_idx))
+    }
+
+    /// Extent of the items in `[start, end)`, including the space laid out
+    /// between them.
+    #[wasm_bindgen]
+    pub fn get_range_size(&mut self, start: usize, end: usize) -> Result<f64, VirtualListError> {
+        if start > end || end > self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidRange,
+                &format!(
+                    "Invalid range {}..{} for {} items",
+                    start, end, self.total_items
+                ),
+            ));
+        }
+        if start == end {
+            return Ok(0.0);
+        }
+        let sizes = self.sizes_before(end)? - self.sizes_before(start)?;
+        Ok(sizes + self.extra_before(end - 1) - self.extra_before(start))
+    }
+
+    /// Ratio of the rendered range's extent to `viewport_size`. Values below
+    /// 1.0 mean the rendered items leave part of the viewport uncovered.
+    #[wasm_bindgen]
+    pub fn viewport_fill_state(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<f64, VirtualListError> {
+        let range = self.get_visible_range(scroll_position, viewport_size)?;
+        let rendered_extent = self.item_offset(range.end)? - self.item_offset(range.start)?;
+        Ok(rendered_extent / viewport_size)
+    }
+
+    /// Thumb size and position for a custom scrollbar. When everything fits
+    /// in the viewport the thumb fills the whole track.
+    #[wasm_bindgen]
+    pub fn scrollbar_metrics(&self, scroll_position: f64, viewport_size: f64) -> ScrollbarMetrics {
+        // The track spans the whole container, including `content_offset`
+        let extent = self.config.content_offset + self.scroll_extent();
+        if viewport_size.is_nan() || viewport_size >= extent {
+            return ScrollbarMetrics {
+                thumb_size_ratio: 1.0,
+                thumb_offset_ratio: 0.0,
+            };
+        }
+        ScrollbarMetrics {
+            thumb_size_ratio: (viewport_size / extent).max(MIN_THUMB_SIZE_RATIO),
+            thumb_offset_ratio: self.position_to_fraction(scroll_position, viewport_size),
+        }
+    }
+
+    /// How far `position` is through the scrollable range, from 0.0 to 1.0.
+    /// Ranges shorter than a pixel are treated as one pixel long.
+    #[wasm_bindgen]
+    pub fn position_to_fraction(&self, position: f64, viewport_size: f64) -> f64 {
+        if position.is_nan() {
+            return 0.0;
+        }
+        (position / self.max_scroll_offset(viewport_size).max(1.0)).clamp(0.0, 1.0)
+    }
+
+    /// The inverse of `position_to_fraction`, clamped to the scrollable range.
+    #[wasm_bindgen]
+    pub fn fraction_to_position(&self, fraction: f64, viewport_size: f64) -> f64 {
+        if fraction.is_nan() {
+            return 0.0;
+        }
+        let max_scroll = self.max_scroll_offset(viewport_size);
+        (fraction.clamp(0.0, 1.0) * max_scroll.max(1.0)).min(max_scroll)
+    }
+
+    #[wasm_bindgen]
+    pub fn batch_update_sizes(&mut self, updates: Vec<JsValue>) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        let parsed_updates: Vec<Result<(usize, f64), String>> = updates
+            .into_iter()
+            .map(|js_val| {
+                let arr = js_val
+                    .dyn_into::<Array>()
+                    .map_err(|_| "Invalid update format".to_string())?;
+                if arr.length() != 2 {
+                    return Err("Each update must be an array of [index, size]".to_string());
+                }
+                let index =
+                    arr.get(0)
+                        .as_f64()
+                        .ok_or("Index must be a number".to_string())? as usize;
+                let size = arr
+                    .get(1)
+                    .as_f64()
+                    .ok_or("Size must be a number".to_string())?;
+                Ok((index, size))
+            })
+            .collect();
+
+        let updates: Vec<(usize, f64)> = parsed_updates
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| convert_error(VirtualListErrorKind::InvalidUpdate, &e))?;
+
+        if let Some(&(index, _)) = updates
+            .iter()
+            .find(|&&(index, _)| index >= self.total_items)
+        {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} out of bounds", index),
+            ));
+        }
+
+        self.apply_size_updates_in_batches(&updates)
+    }
+
+    /// Sets `sizes[i]` for item `indices[i]`, like `batch_update_sizes`
+    /// without the `[index, size]` arrays. Everything is validated before any
+    /// size changes.
+    #[wasm_bindgen]
+    pub fn update_item_sizes(
+        &mut self,
+        indices: &[u32],
+        sizes: &[f64],
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if indices.len() != sizes.len() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidUpdate,
+                "indices and sizes must have the same length",
+            ));
+        }
+        if let Some(&index) = indices
+            .iter()
+            .find(|&&index| index as usize >= self.total_items)
+        {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} out of bounds", index),
+            ));
+        }
+        if let Some(&size) = sizes.iter().find(|size| !is_valid_size(**size)) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size: {}", size),
+            ));
+        }
+        let updates: Vec<(usize, f64)> = indices
+            .iter()
+            .map(|&index| index as usize)
+            .zip(sizes.iter().copied())
+            .collect();
+        self.apply_size_updates_in_batches(&updates)
+    }
+
+    /// Sets `sizes[k]` for item `start + k`, for measuring a contiguous block
+    /// such as a rendered screen. Everything is validated before any size
+    /// changes.
+    #[wasm_bindgen]
+    pub fn set_item_sizes_range(
+        &mut self,
+        start: usize,
+        sizes: &[f64],
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if start
+            .checked_add(sizes.len())
+            .is_none_or(|end| end > self.total_items)
+        {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!(
+                    "Range of {} sizes at {} exceeds {} items",
+                    sizes.len(),
+                    start,
+                    self.total_items
+                ),
+            ));
+        }
+        if let Some(&size) = sizes.iter().find(|size| !is_valid_size(**size)) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size: {}", size),
+            ));
+        }
+        let updates: Vec<(usize, f64)> = (start..).zip(sizes.iter().copied()).collect();
+        self.apply_size_updates_in_batches(&updates)
+    }
+
+    // Every size is checked before the first batch, so a bad entry can't
+    // leave the list half-updated.
+    fn apply_size_updates_in_batches(
+        &mut self,
+        updates: &[(usize, f64)],
+    ) -> Result<(), VirtualListError> {
+        if let Some(&(_, size)) = updates.iter().find(|&&(_, size)| !is_valid_size(size)) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size: {}", size),
+            ));
+        }
+        for batch in updates.chunks(self.config.update_batch_size) {
+            self.apply_size_updates(batch)?;
+        }
+        Ok(())
+    }
+
+    fn apply_size_updates(&mut self, updates: &[(usize, f64)]) -> Result<(), VirtualListError> {
+        let mut chunk_updates: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+        for &(index, new_size) in updates {
+            let chunk_idx = index / self.chunk_size;
+            let item_idx = index % self.chunk_size;
+            chunk_updates
+                .entry(chunk_idx)
+                .or_default()
+                .push((item_idx, self.clamp_item_size(new_size)));
+        }
+
+        let mut measured_sum = 0.0;
+        for (chunk_idx, updates) in chunk_updates {
+            let chunk = self.get_or_create_chunk(chunk_idx).map_err(|e| {
+                convert_error(VirtualListErrorKind::ChunkError, &format!("{:?}", e))
+            })?;
+            let mut total_diff = 0.0;
+            for (item_idx, new_size) in updates {
+                let diff = chunk
+                    .update_size(item_idx, new_size)
+                    .map_err(|e| convert_error(VirtualListErrorKind::UpdateError, &e))?;
+                total_diff += diff;
+                measured_sum += new_size;
+            }
+            // Applied per chunk so the offsets never lag the chunks
+            self.chunk_offsets.add(chunk_idx, total_diff);
+            self.total_size += total_diff;
+        }
+        self.record_measurements(updates.len() as u64, measured_sum);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_total_items(&mut self, new_total: usize) -> Result<(), VirtualListError> {
+        self.check_not_frozen()?;
+        if new_total == self.total_items {
+            return Ok(());
+        }
+        self.begin_mutation()?;
+        self.size_hints.retain(|&index, _| index < new_total);
+        self.pinned.retain(|&index| index < new_total);
+        self.item_kinds.retain(|&index, _| index < new_total);
+        if self
+            .sections
+            .last()
+            .is_some_and(|&(index, _)| index >= new_total)
+        {
+            let end = self
+                .sections
+                .partition_point(|&(index, _)| index < new_total);
+            let sections = self.sections[..end].to_vec();
+            self.replace_sections(sections);
+        }
+        let new_num_chunks = new_total.div_ceil(self.chunk_size);
+        self.chunk_estimates
+            .retain(|&chunk_idx, _| chunk_idx < new_num_chunks);
+        self.chunk_access
+            .retain(|&chunk_idx, _| chunk_idx < new_num_chunks);
+        let old_num_chunks = self.num_chunks();
+        // Only the old and the new last chunk can change length; every other
+        // surviving chunk keeps its total and every new one is uniform
+        let mut resized: Vec<(usize, f64)> = [old_num_chunks, new_num_chunks]
+            .into_iter()
+            .filter(|&count| count > 0 && count <= new_num_chunks)
+            .map(|count| (count - 1, self.chunk_offsets.deviation(count - 1)))
+            .collect();
+        resized.dedup_by_key(|&mut (chunk_idx, _)| chunk_idx);
+
+        let dropped = self.loaded_chunk_spans(false, |chunk_idx| chunk_idx >= new_num_chunks);
+        self.total_items = new_total;
+        self.chunks
+            .retain(|&chunk_idx, _| chunk_idx < new_num_chunks);
+        self.chunk_offsets.resize(new_total);
+        for (chunk_idx, old_deviation) in resized {
+            self.fit_chunk_to_total(chunk_idx);
+            let deviation = self.chunk_deviation(chunk_idx);
+            self.chunk_offsets.add(chunk_idx, deviation - old_deviation);
+        }
+        self.total_size = self.chunk_offsets.prefix(new_num_chunks);
+        self.notify_chunk_spans(false, dropped)
+    }
+
+    // Truncates a loaded chunk past the end of the list, or extends it with
+    // estimates up to a full chunk, after `total_items` changed.
+    fn fit_chunk_to_total(&mut self, chunk_idx: usize) {
+        let Some(chunk) = self.chunks.get(&chunk_idx) else {
+            return;
+        };
+        let chunk_start = chunk_idx * self.chunk_size;
+        let len = self.items_in_chunk(chunk_idx);
+        let tail: Vec<f64> = (chunk_start + chunk.len()..chunk_start + len)
+            .map(|i| self.estimated_item_size(i))
+            .collect();
+        if let Some(chunk) = self.chunks.get_mut(&chunk_idx) {
+            chunk.resize(len, tail);
+        }
+    }
+
+    /// Grows the list by `count` estimated items and returns the new
+    /// `total_size`.
+    #[wasm_bindgen]
+    pub fn append_items(&mut self, count: usize) -> Result<f64, VirtualListError> {
+        let Some(new_total) = self.total_items.checked_add(count) else {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Appending {} items overflows the item count", count),
+            ));
+        };
+        self.set_total_items(new_total)?;
+        Ok(self.total_size())
+    }
+
+    /// Re-chunks the list in place. Sizes of items in loaded chunks are kept
+    /// by index; chunk-level learned estimates and access history are reset.
+    #[wasm_bindgen]
+    pub fn set_chunk_size(&mut self, new_chunk_size: usize) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if new_chunk_size == 0 {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidConfig,
+                "chunk_size must be positive",
+            ));
+        }
+        if new_chunk_size == self.chunk_size {
+            return Ok(());
+        }
+
+        let loaded_sizes = self.loaded_item_sizes();
+        let dropped = self.loaded_chunk_spans(false, |_| true);
+        self.chunk_size = new_chunk_size;
+        self.chunks.clear();
+        self.chunk_access.clear();
+        self.chunk_estimates.clear();
+        self.restore_sizes(loaded_sizes)?;
+        self.rebuild_cumulative_sizes();
+        // The kept sizes now sit in chunks of the new size
+        self.notify_chunk_spans(false, dropped)?;
+        self.notify_chunk_spans(true, self.loaded_chunk_spans(true, |_| true))
+    }
+
+    // (global index, size, measured) of every item in a loaded chunk.
+    fn loaded_item_sizes(&self) -> Vec<(usize, f64, bool)> {
+        let mut sizes = Vec::new();
+        for (&chunk_idx, chunk) in &self.chunks {
+            let chunk_start = chunk_idx * self.chunk_size;
+            sizes.extend(
+                chunk
+                    .iter_sizes()
+                    .zip(chunk.measured.iter())
+                    .enumerate()
+                    .map(|(i, (size, measured))| (chunk_start + i, size, measured)),
+            );
+        }
+        sizes
+    }
+
+    #[wasm_bindgen]
+    pub fn is_item_measured(&self, index: usize) -> Result<bool, VirtualListError> {
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        Ok(self
+            .chunks
+            .get(&(index / self.chunk_size))
+            .is_some_and(|chunk| chunk.measured.get(index % self.chunk_size)))
+    }
+
+    /// Global indices of items whose size was explicitly set, in order.
+    /// Items in unloaded chunks are never reported.
+    #[wasm_bindgen]
+    pub fn measured_indices(&self) -> Vec<u32> {
+        self.loaded_item_sizes()
+            .into_iter()
+            .filter(|&(_, _, measured)| measured)
+            .map(|(index, _, _)| index as u32)
+            .collect()
+    }
+
+    /// Share of the items' total size that comes from measured sizes in
+    /// loaded chunks, from 0.0 to 1.0. Gaps, headers and padding are not
+    /// counted. An empty or zero-sized list reports 0.
+    #[wasm_bindgen]
+    pub fn measured_size_fraction(&self) -> f64 {
+        if self.total_size <= 0.0 {
+            return 0.0;
+        }
+        let measured: f64 = self
+            .loaded_item_sizes()
+            .into_iter()
+            .filter(|&(_, _, measured)| measured)
+            .map(|(_, size, _)| size)
+            .sum();
+        (measured / self.total_size).clamp(0.0, 1.0)
+    }
+
+    /// The complement of `measured_size_fraction`: the share that is still
+    /// estimated.
+    #[wasm_bindgen]
+    pub fn estimated_size_portion(&self) -> f64 {
+        1.0 - self.measured_size_fraction()
+    }
+
+    /// Counts of measured sizes in loaded chunks per `bucket_width`-wide
+    /// bucket, with everything past the last bucket counted in it. For
+    /// picking a better `estimated_size`.
+    #[wasm_bindgen]
+    pub fn size_histogram(
+        &self,
+        bucket_width: f64,
+        num_buckets: usize,
+    ) -> Result<Vec<u32>, VirtualListError> {
+        if !bucket_width.is_finite() || bucket_width <= 0.0 {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidConfig,
+                &format!("Invalid bucket width: {}", bucket_width),
+            ));
+        }
+        let mut counts = vec![0; num_buckets];
+        if num_buckets == 0 {
+            return Ok(counts);
+        }
+        for (_, size, measured) in self.loaded_item_sizes() {
+            if measured {
+                let bucket = ((size / bucket_width) as usize).min(num_buckets - 1);
+                counts[bucket] += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    // Writes `(index, size, measured)` entries straight into their chunks,
+    // materializing them without eviction. Callers must rebuild cumulative
+    // sizes afterwards.
+    fn restore_sizes(&mut self, sizes: Vec<(usize, f64, bool)>) -> Result<(), VirtualListError> {
+        for (index, size, measured) in sizes {
+            let chunk_idx = index / self.chunk_size;
+            if !self.chunks.contains_key(&chunk_idx) {
+                self.create_chunk(chunk_idx)?;
+                self.access_counter += 1;
+                self.chunk_access.insert(chunk_idx, self.access_counter);
+            }
+            let chunk = self.chunks.get_mut(&chunk_idx).unwrap();
+            let item_idx = index % self.chunk_size;
+            chunk
+                .set_size(item_idx, size)
+                .map_err(|e| convert_error(VirtualListErrorKind::UpdateError, &e))?;
+            chunk.measured.set(item_idx, measured);
+        }
+        Ok(())
+    }
+
+    /// Human-readable summary of the internal state for bug reports. Long
+    /// lists are truncated.
+    #[wasm_bindgen]
+    pub fn debug_state(&self) -> String {
+        let loaded = self.chunks.len();
+        let mut access: Vec<(usize, u64)> = self
+            .chunk_access
+            .iter()
+            .map(|(&chunk_idx, &access)| (chunk_idx, access))
+            .collect();
+        access.sort_unstable();
+        let mut out = String::new();
+        let _ = writeln!(out, "total_items: {}", self.total_items);
+        let _ = writeln!(out, "chunk_size: {}", self.chunk_size);
+        let _ = writeln!(
+            out,
+            "orientation: {} ({})",
+            self.orientation.axis(),
+            self.orientation.dimension()
+        );
+        let _ = writeln!(out, "estimated_size: {}", self.estimated_size);
+        let _ = writeln!(
+            out,
+            "total_size: {} (items: {})",
+            self.scroll_extent(),
+            self.total_size
+        );
+        let _ = writeln!(out, "loaded_chunks: {} of {}", loaded, self.num_chunks());
+        let _ = writeln!(out, "frozen: {}", self.frozen);
+        let _ = writeln!(
+            out,
+            "cumulative_sizes: {}",
+            debug_list(&self.cumulative_sizes())
+        );
+        let _ = write!(out, "chunk_access: {}", debug_list(&access));
+        out
+    }
+
+    /// Rebuilds `cumulative_sizes` and the total from loaded chunk totals and
+    /// the estimates for unloaded chunks. O(chunks) and idempotent; use after
+    /// bulk operations if `verify_integrity` reports drift.
+    #[wasm_bindgen]
+    pub fn recompute(&mut self) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        self.rebuild_cumulative_sizes();
+        Ok(())
+    }
+
+    /// Debug check of the size model against a from-scratch recomputation.
+    /// Fails with `InvalidState` describing the first inconsistency found.
+    #[wasm_bindgen]
+    pub fn verify_integrity(&self) -> Result<(), VirtualListError> {
+        self.check_integrity()
+            .map_err(|e| convert_error(VirtualListErrorKind::InvalidState, &e))
+    }
+
+    fn check_integrity(&self) -> Result<(), String> {
+        // Incremental updates accumulate rounding, so compare loosely
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1.0);
+        let num_chunks = self.total_items.div_ceil(self.chunk_size);
+        if self.chunk_offsets.len() != num_chunks {
+            return Err(format!(
+                "Expected {} chunks, found {} cumulative sizes",
+                num_chunks,
+                self.chunk_offsets.len()
+            ));
+        }
+        if let Some((&chunk_idx, _)) = self.chunks.last_key_value()
+            && chunk_idx >= num_chunks
+        {
+            return Err(format!(
+                "Chunk {} is loaded past the last chunk {}",
+                chunk_idx,
+                num_chunks.wrapping_sub(1)
+            ));
+        }
+        let mut expected = 0.0;
+        for chunk_idx in 0..num_chunks {
+            expected += match self.chunks.get(&chunk_idx) {
+                Some(chunk) => {
+                    let len = self.items_in_chunk(chunk_idx);
+                    if chunk.len() != len {
+                        return Err(format!(
+                            "Chunk {} holds {} items, expected {}",
+                            chunk_idx,
+                            chunk.len(),
+                            len
+                        ));
+                    }
+                    let sum: f64 = chunk.iter_sizes().sum();
+                    if !close(chunk.total_size, sum) || !close(chunk.prefix(len), sum) {
+                        return Err(format!(
+                            "Chunk {} total {} does not match its sizes ({})",
+                            chunk_idx, chunk.total_size, sum
+                        ));
+                    }
+                    chunk.total_size
+                }
+                None => self.estimated_chunk_total(chunk_idx),
+            };
+            let cumulative = self.chunk_offsets.prefix(chunk_idx + 1);
+            if !close(cumulative, expected) {
+                return Err(format!(
+                    "Cumulative size of chunk {} is {}, expected {}",
+                    chunk_idx, cumulative, expected
+                ));
+            }
+        }
+        if !close(self.total_size, expected) {
+            return Err(format!(
+                "Total size is {}, expected {}",
+                self.total_size, expected
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compact little-endian snapshot of the size model: total_items,
+    /// chunk_size, estimated_size, orientation, the dynamic-estimate
+    /// counters and applied mean, then sparse lists of size hints, kind estimates, item kinds,
+    /// chunk estimates, measured sizes and loaded sizes that no longer match
+    /// their estimate. Restoring it reproduces `total_size` and every
+    /// position exactly.
+    #[wasm_bindgen]
+    pub fn serialize_state(&self) -> Vec<u8> {
+        let mut hints: Vec<(usize, f64)> = self.size_hints.iter().map(|(&i, &s)| (i, s)).collect();
+        hints.sort_unstable_by_key(|&(index, _)| index);
+        let mut chunk_estimates: Vec<(usize, f64)> = self
+            .chunk_estimates
+            .iter()
+            .map(|(&chunk_idx, &size)| (chunk_idx, size))
+            .collect();
+        chunk_estimates.sort_unstable_by_key(|&(chunk_idx, _)| chunk_idx);
+        let mut kind_estimates: Vec<(u32, f64)> = self
+            .kind_estimates
+            .iter()
+            .map(|(&kind, &size)| (kind, size))
+            .collect();
+        kind_estimates.sort_unstable_by_key(|&(kind, _)| kind);
+        let mut item_kinds: Vec<(usize, u32)> = self
+            .item_kinds
+            .iter()
+            .map(|(&index, &kind)| (index, kind))
+            .collect();
+        item_kinds.sort_unstable_by_key(|&(index, _)| index);
+        let mut measured = Vec::new();
+        let mut stale = Vec::new();
+        for (index, size, is_measured) in self.loaded_item_sizes() {
+            if is_measured {
+                measured.push((index, size));
+            } else if size != self.estimated_item_size(index) {
+                stale.push((index, size));
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(
+            74 + 16 * (hints.len() + chunk_estimates.len() + measured.len() + stale.len())
+                + 12 * (kind_estimates.len() + item_kinds.len()),
+        );
+        bytes.push(STATE_FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.total_items as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.chunk_size as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.estimated_size.to_le_bytes());
+        bytes.push(self.orientation as u8);
+        bytes.extend_from_slice(&self.measured_count.to_le_bytes());
+        bytes.extend_from_slice(&self.measured_sum.to_le_bytes());
+        bytes.extend_from_slice(&self.dynamic_estimate.to_le_bytes());
+        bytes.extend_from_slice(&(hints.len() as u64).to_le_bytes());
+        for &(index, size) in &hints {
+            bytes.extend_from_slice(&(index as u64).to_le_bytes());
+            bytes.extend_from_slice(&size.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(kind_estimates.len() as u64).to_le_bytes());
+        for &(kind, size) in &kind_estimates {
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(&size.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(item_kinds.len() as u64).to_le_bytes());
+        for &(index, kind) in &item_kinds {
+            bytes.extend_from_slice(&(index as u64).to_le_bytes());
+            bytes.extend_from_slice(&kind.to_le_bytes());
+        }
+        for entries in [&chunk_estimates, &measured, &stale] {
+            bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+            for &(index, size) in entries.iter() {
+                bytes.extend_from_slice(&(index as u64).to_le_bytes());
+                bytes.extend_from_slice(&size.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[wasm_bindgen]
+    pub fn deserialize_state(
+        bytes: &[u8],
+        config: VirtualListConfig,
+    ) -> Result<VirtualList, VirtualListError> {
+        let mut reader = ByteReader::new(bytes);
+        let invalid = |e: String| convert_error(VirtualListErrorKind::InvalidState, &e);
+        let version = reader.read_u8().map_err(invalid)?;
+        if version != STATE_FORMAT_VERSION {
+            return Err(invalid(format!("Unsupported state version {}", version)));
+        }
+        let total_items = reader.read_usize().map_err(invalid)?;
+        let chunk_size = reader.read_usize().map_err(invalid)?;
+        if chunk_size == 0 {
+            return Err(invalid("Chunk size must be greater than zero".to_string()));
+        }
+        // The header is untrusted; a restored list rebuilds its chunk totals
+        // in one allocation, so refuse chunk counts that cannot be allocated
+        // instead of aborting later.
+        let num_chunks = total_items.div_ceil(chunk_size);
+        if Vec::<f64>::new()
+            .try_reserve_exact(num_chunks.saturating_add(1))
+            .is_err()
+        {
+            return Err(invalid(format!("Too many chunks: {}", num_chunks)));
+        }
+        let estimated_size = reader.read_f64().map_err(invalid)?;
+        let orientation = match reader.read_u8().map_err(invalid)? {
+            0 => Orientation::Vertical,
+            1 => Orientation::Horizontal,
+            other => return Err(invalid(format!("Invalid orientation {}", other))),
+        };
+        let measured_count = reader.read_u64().map_err(invalid)?;
+        let measured_sum = reader.read_f64().map_err(invalid)?;
+        if !is_valid_size(measured_sum) {
+            return Err(invalid(format!("Invalid measured sum: {}", measured_sum)));
+        }
+        let dynamic_estimate = reader.read_f64().map_err(invalid)?;
+        if !is_valid_size(dynamic_estimate) {
+            return Err(invalid(format!(
+                "Invalid dynamic estimate: {}",
+                dynamic_estimate
+            )));
+        }
+        let hints = reader.read_entries(total_items).map_err(invalid)?;
+        let kind_count = reader.read_usize().map_err(invalid)?;
+        let mut kind_estimates = HashMap::new();
+        for _ in 0..kind_count {
+            let kind = reader.read_u32().map_err(invalid)?;
+            let size = reader.read_f64().map_err(invalid)?;
+            if !is_valid_size(size) {
+                return Err(invalid(format!("Invalid size: {}", size)));
+            }
+            kind_estimates.insert(kind, size);
+        }
+        let item_kind_count = reader.read_usize().map_err(invalid)?;
+        let mut item_kinds = HashMap::new();
+        for _ in 0..item_kind_count {
+            let index = reader.read_usize().map_err(invalid)?;
+            let kind = reader.read_u32().map_err(invalid)?;
+            if index >= total_items {
+                return Err(invalid(format!("Index {} out of bounds", index)));
+            }
+            item_kinds.insert(index, kind);
+        }
+        let chunk_estimates = reader.read_entries(num_chunks).map_err(invalid)?;
+        let measured = reader.read_entries(total_items).map_err(invalid)?;
+        let stale = reader.read_entries(total_items).map_err(invalid)?;
+        if !reader.is_empty() {
+            return Err(invalid("Trailing bytes after state".to_string()));
+        }
+
+        let mut list =
+            VirtualList::new(total_items, chunk_size, estimated_size, orientation, config)?;
+        list.measured_count = measured_count;
+        list.measured_sum = measured_sum;
+        list.dynamic_estimate = dynamic_estimate;
+        list.size_hints.extend(hints);
+        list.kind_estimates = kind_estimates;
+        list.item_kinds = item_kinds;
+        list.chunk_estimates.extend(chunk_estimates);
+        // Estimates go first so chunks materialized here start from the
+        // same sizes they had when serialized.
+        list.restore_sizes(
+            measured
+                .into_iter()
+                .map(|(index, size)| (index, size, true))
+                .chain(stale.into_iter().map(|(index, size)| (index, size, false)))
+                .collect(),
+        )?;
+        list.rebuild_chunk_offsets();
+        Ok(list)
+    }
+
+    /// Measured sizes as `{"chunk_size":N,"total_items":M,"sizes":{"12":34.0}}`,
+    /// for attaching to bug reports.
+    #[wasm_bindgen]
+    pub fn export_sizes_json(&self) -> String {
+        let export = SizesExport {
+            chunk_size: self.chunk_size,
+            total_items: self.total_items,
+            sizes: self
+                .loaded_item_sizes()
+                .into_iter()
+                .filter(|&(_, _, measured)| measured)
+                .map(|(index, size, _)| (index, size))
+                .collect(),
+        };
+        serde_json::to_string(&export).unwrap()
+    }
+
+    /// Applies sizes produced by `export_sizes_json` through the regular
+    /// batch update path.
+    #[wasm_bindgen]
+    pub fn import_sizes_json(&mut self, json: &str) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        let import: SizesExport = serde_json::from_str(json)
+            .map_err(|e| convert_error(VirtualListErrorKind::InvalidJson, &e.to_string()))?;
+        if let Some(&index) = import
+            .sizes
+            .keys()
+            .find(|&&index| index >= self.total_items)
+        {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} out of bounds", index),
+            ));
+        }
+        let updates: Vec<(usize, f64)> = import.sizes.into_iter().collect();
+        self.apply_size_updates_in_batches(&updates)
+    }
+
+    #[wasm_bindgen]
+    pub fn unload_chunk(&mut self, chunk_idx: usize) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if chunk_idx >= self.num_chunks() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidChunkIndex,
+                &format!("Chunk index {} out of bounds", chunk_idx),
+            ));
+        }
+        self.drop_chunk(chunk_idx)
+    }
+
+    // `unload_chunk` without the bounds check or the generation bump.
+    fn drop_chunk(&mut self, chunk_idx: usize) -> Result<(), VirtualListError> {
+        if let Some(chunk) = self.chunks.remove(&chunk_idx) {
+            let old_total = chunk.total_size;
+            let estimated_total = self.estimated_chunk_total(chunk_idx);
+            let diff = estimated_total - old_total;
+            self.update_cumulative_sizes(chunk_idx, diff)
+                .map_err(|e| convert_error(VirtualListErrorKind::CumulativeUpdateError, &e))?;
+            self.chunk_access.remove(&chunk_idx);
+            if self.on_chunk_unloaded.is_some() {
+                self.notify_chunk_callback(false, chunk_idx, chunk.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the list's section headers. `indices[k]` is the item the
+    /// k-th header sits directly before and `header_sizes[k]` its size.
+    /// Headers are part of the scroll extent and shift every following
+    /// item, like `gap`; positions inside a header resolve to the item
+    /// before it.
+    #[wasm_bindgen]
+    pub fn set_sections(
+        &mut self,
+        indices: Vec<u32>,
+        header_sizes: Vec<f64>,
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if indices.len() != header_sizes.len() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSections,
+                "indices and header_sizes must have the same length",
+            ));
+        }
+        let mut sections: Vec<(usize, f64)> = indices
+            .into_iter()
+            .map(|index| index as usize)
+            .zip(header_sizes)
+            .collect();
+        sections.sort_by_key(|&(index, _)| index);
+        for (k, &(index, size)) in sections.iter().enumerate() {
+            if index >= self.total_items {
+                return Err(convert_error(
+                    VirtualListErrorKind::IndexOutOfBounds,
+                    &format!("Index {} exceeds total items", index),
+                ));
+            }
+            if !is_valid_size(size) {
+                return Err(convert_error(
+                    VirtualListErrorKind::InvalidSize,
+                    &format!("Invalid header size: {}", size),
+                ));
+            }
+            if k > 0 && sections[k - 1].0 == index {
+                return Err(convert_error(
+                    VirtualListErrorKind::InvalidSections,
+                    &format!("Duplicate section at index {}", index),
+                ));
+            }
+        }
+        self.replace_sections(sections);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Leading edge of the header of section `section`.
+    #[wasm_bindgen]
+    pub fn section_header_offset(&mut self, section: usize) -> Result<f64, VirtualListError> {
+        let Some(&(index, size)) = self.sections.get(section) else {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Section {} out of bounds", section),
+            ));
+        };
+        Ok(self.item_offset(index)? - size)
+    }
+
+    /// Takes item `index` out of the scrolled flow, e.g. for a frozen summary
+    /// row rendered at the top. Its size no longer counts toward the scroll
+    /// extent, and it is reported by `VisibleRange::pinned` instead.
+    #[wasm_bindgen]
+    pub fn pin_row(&mut self, index: usize) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        if let Err(position) = self.pinned.binary_search(&index) {
+            self.pinned.insert(position, index);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn unpin_row(&mut self, index: usize) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if let Ok(position) = self.pinned.binary_search(&index) {
+            self.pinned.remove(position);
+        }
+        Ok(())
+    }
+
+    /// The header that sticks to the viewport's leading edge: the last one
+    /// scrolled past, pushed out by the next header once they meet.
+    #[wasm_bindgen]
+    pub fn sticky_header_offset(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+    ) -> Result<StickyHeader, VirtualListError> {
+        let (scroll_position, _) = self.visible_window(scroll_position, viewport_size)?;
+        let (item_idx, _) = self
+            .find_item_at_position(scroll_position)
+            .map_err(|e| convert_error(VirtualListErrorKind::PositionError, &e))?;
+        // Headers up to the one before the next item may start in this slot
+        let mut passed = self.sections_through(item_idx + 1);
+        while passed > 0 && self.section_header_offset(passed - 1)? > scroll_position {
+            passed -= 1;
+        }
+        if passed == 0 {
+            return Ok(StickyHeader {
+                section: None,
+                offset: 0.0,
+            });
+        }
+        let section = passed - 1;
+        let mut offset = 0.0;
+        if passed < self.sections.len() {
+            let next_start = self.section_header_offset(passed)?;
+            let header_size = self.sections[section].1;
+            offset = (next_start - scroll_position - header_size).min(0.0);
+        }
+        Ok(StickyHeader {
+            section: Some(section),
+            offset,
+        })
+    }
+
+    /// Replaces the estimate used for unmeasured items. Unloaded chunks are
+    /// re-estimated; loaded chunks keep their sizes.
+    #[wasm_bindgen]
+    pub fn set_estimated_size(&mut self, new_estimate: f64) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if !is_valid_size(new_estimate) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid estimated size: {}", new_estimate),
+            ));
+        }
+        self.estimated_size = new_estimate;
+        self.rebuild_cumulative_sizes();
+        Ok(())
+    }
+
+    /// Whether chunk `chunk_idx` is resident. Unlike loading, this leaves the
+    /// access order untouched.
+    #[wasm_bindgen]
+    pub fn is_chunk_loaded(&self, chunk_idx: usize) -> Result<bool, VirtualListError> {
+        if chunk_idx >= self.num_chunks() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidChunkIndex,
+                &format!("Chunk index {} out of bounds", chunk_idx),
+            ));
+        }
+        Ok(self.chunks.contains_key(&chunk_idx))
+    }
+
+    /// Loaded chunk indices from least to most recently accessed: the order
+    /// in which eviction would unload them.
+    #[wasm_bindgen]
+    pub fn access_order(&self) -> Vec<u32> {
+        let mut by_recency: Vec<(u64, usize)> = self
+            .chunk_access
+            .iter()
+            .filter(|&(chunk_idx, _)| self.chunks.contains_key(chunk_idx))
+            .map(|(&chunk_idx, &access)| (access, chunk_idx))
+            .collect();
+        by_recency.sort_unstable();
+        by_recency
+            .into_iter()
+            .map(|(_, chunk_idx)| chunk_idx as u32)
+            .collect()
+    }
+
+    /// Capacity hint before measuring about `items` items in one flush:
+    /// reserves room for the chunks they span in the loaded-chunk
+    /// bookkeeping, up to `max_loaded_chunks`, so the flush doesn't rehash
+    /// it as chunks load. Doesn't change the layout.
+    #[wasm_bindgen]
+    pub fn reserve_measurements(&mut self, items: usize) {
+        let mut chunks = items
+            .div_ceil(self.chunk_size)
+            .saturating_add(1)
+            .min(self.num_chunks());
+        if let Some(max) = self.config.max_loaded_chunks {
+            chunks = chunks.min(max);
+        }
+        self.chunk_access
+            .reserve(chunks.saturating_sub(self.chunk_access.len()));
+    }
+
+    /// Unloads every chunk except the `keep_most_recent` most recently
+    /// accessed ones, independent of `max_loaded_chunks`. Returns how many
+    /// chunks were unloaded.
+    #[wasm_bindgen]
+    pub fn evict_older_than(&mut self, keep_most_recent: usize) -> Result<usize, VirtualListError> {
+        self.begin_mutation()?;
+        self.evict_all_but(keep_most_recent)
+    }
+
+    fn evict_all_but(&mut self, keep_most_recent: usize) -> Result<usize, VirtualListError> {
+        let mut by_recency: Vec<(usize, u64)> = self
+            .chunk_access
+            .iter()
+            .map(|(&chunk_idx, &access)| (chunk_idx, access))
+            .collect();
+        by_recency.sort_unstable_by_key(|&(_, access)| cmp::Reverse(access));
+        let mut evicted = 0;
+        for &(chunk_idx, _) in by_recency.iter().skip(keep_most_recent) {
+            self.drop_chunk(chunk_idx)?;
+            evicted += 1;
+        }
+        Ok(evicted)
+    }
+
+    /// Unloads every chunk outside the viewport's chunk span widened by
+    /// `keep_margin_chunks` on each side, e.g. after a jump scroll. Returns
+    /// how many chunks were unloaded.
+    #[wasm_bindgen]
+    pub fn trim_loaded_to_visible(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        keep_margin_chunks: usize,
+    ) -> Result<usize, VirtualListError> {
+        let (start, end) = self.visible_window(scroll_position, viewport_size)?;
+        self.begin_mutation()?;
+        let first = self
+            .chunk_at_position(start)
+            .saturating_sub(keep_margin_chunks);
+        let last = self
+            .chunk_at_position(end)
+            .saturating_add(keep_margin_chunks);
+        let mut evicted = 0;
+        let outside: Vec<usize> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&chunk_idx| chunk_idx < first || chunk_idx > last)
+            .collect();
+        for chunk_idx in outside {
+            self.drop_chunk(chunk_idx)?;
+            evicted += 1;
+        }
+        Ok(evicted)
+    }
+
+    /// Loads up to `ahead_chunks` chunks after and `behind_chunks` before
+    /// the viewport's chunk span, nearest first and ahead before behind, to
+    /// keep the leading edge warm while scrolling one way. "Ahead" follows
+    /// list order. Under `max_loaded_chunks`, prefetching stops before it
+    /// would evict the viewport's chunks or ones it just loaded. Returns how
+    /// many chunks were created.
+    #[wasm_bindgen]
+    pub fn prefetch_around(
+        &mut self,
+        scroll_position: f64,
+        viewport_size: f64,
+        ahead_chunks: usize,
+        behind_chunks: usize,
+    ) -> Result<usize, VirtualListError> {
+        let (start, end) = self.visible_window(scroll_position, viewport_size)?;
+        let first = self.chunk_at_position(start);
+        let last = self.chunk_at_position(end);
+        // Refresh the viewport's chunks so eviction passes over them
+        for chunk_idx in first..=last {
+            if self.chunk_access.contains_key(&chunk_idx) {
+                self.access_counter += 1;
+                self.chunk_access.insert(chunk_idx, self.access_counter);
+            }
+        }
+        let mut budget = self
+            .config
+            .max_loaded_chunks
+            .map_or(usize::MAX, |max| max.saturating_sub(last - first + 1));
+        let ahead = (last + 1..self.num_chunks()).take(ahead_chunks);
+        let behind = (0..first).rev().take(behind_chunks);
+        let mut created = 0;
+        for chunk_idx in ahead.chain(behind) {
+            if budget == 0 {
+                break;
+            }
+            budget -= 1;
+            if !self.chunks.contains_key(&chunk_idx) {
+                created += 1;
+            }
+            self.get_or_create_chunk(chunk_idx)?;
+        }
+        Ok(created)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_item_size_hint(&mut self, index: usize, size: f64) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if index >= self.total_items {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        if !is_valid_size(size) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid size hint: {}", size),
+            ));
+        }
+        let old_size = self.estimated_item_size(index);
+        self.size_hints.insert(index, size);
+        self.estimate_changed(index, old_size);
+        Ok(())
+    }
+
+    /// Sets the estimate for each item kind, e.g. text rows and image rows.
+    /// Unmeasured items assigned a kind with `assign_item_kinds` use it in
+    /// place of the chunk or global estimate; size hints still take priority.
+    /// Like other estimates, this only affects chunks that are not loaded.
+    #[wasm_bindgen]
+    pub fn set_item_kind_estimates(
+        &mut self,
+        kinds: &[u32],
+        estimates: &[f64],
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if kinds.len() != estimates.len() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidKinds,
+                "kinds and estimates must have the same length",
+            ));
+        }
+        if let Some(&size) = estimates.iter().find(|size| !is_valid_size(**size)) {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidSize,
+                &format!("Invalid kind estimate: {}", size),
+            ));
+        }
+        let changed: HashMap<u32, f64> = kinds
+            .iter()
+            .copied()
+            .zip(estimates.iter().copied())
+            .collect();
+        let affected: Vec<(usize, f64)> = self
+            .item_kinds
+            .iter()
+            .filter(|(_, kind)| changed.contains_key(kind))
+            .map(|(&index, _)| (index, self.estimated_item_size(index)))
+            .collect();
+        self.kind_estimates.extend(changed);
+        for (index, old_size) in affected {
+            self.estimate_changed(index, old_size);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn assign_item_kinds(
+        &mut self,
+        indices: &[u32],
+        kinds: &[u32],
+    ) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        if indices.len() != kinds.len() {
+            return Err(convert_error(
+                VirtualListErrorKind::InvalidKinds,
+                "indices and kinds must have the same length",
+            ));
+        }
+        if let Some(&index) = indices
+            .iter()
+            .find(|&&index| index as usize >= self.total_items)
+        {
+            return Err(convert_error(
+                VirtualListErrorKind::IndexOutOfBounds,
+                &format!("Index {} exceeds total items", index),
+            ));
+        }
+        for (&index, &kind) in indices.iter().zip(kinds) {
+            let index = index as usize;
+            let old_size = self.estimated_item_size(index);
+            self.item_kinds.insert(index, kind);
+            self.estimate_changed(index, old_size);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn propagate_estimates(&mut self) -> Result<(), VirtualListError> {
+        self.begin_mutation()?;
+        let num_chunks = self.num_chunks();
+        let averages: Vec<Option<f64>> = (0..num_chunks)
+            .map(|chunk_idx| {
+                self.chunks
+                    .get(&chunk_idx)
+                    .filter(|chunk| chunk.len() > 0)
+                    .map(|chunk| chunk.total_size / chunk.len() as f64)
+            })
+            .collect();
+
+        // Nearest resident chunk on each side, as (distance, average size)
+        let mut nearest_before: Vec<Option<(usize, f64)>> = vec![None; num_chunks];
+        let mut last_seen = None;
+        for (i, average) in averages.iter().enumerate() {
+            if let Some(average) = average {
+                last_seen = Some((i, *average));
+            }
+            nearest_before[i] = last_seen.map(|(j, average)| (i - j, average));
+        }
+        let mut nearest_after: Vec<Option<(usize, f64)>> = vec![None; num_chunks];
+        last_seen = None;
+        for (i, average) in averages.iter().enumerate().rev() {
+            if let Some(average) = average {
+                last_seen = Some((i, *average));
+            }
+            nearest_after[i] = last_seen.map(|(j, average)| (j - i, average));
+        }
+
+        self.chunk_estimates.clear();
+        for chunk_idx in 0..num_chunks {
+            if self.chunks.contains_key(&chunk_idx) {
+                continue;
+            }
+            let estimate = match (nearest_before[chunk_idx], nearest_after[chunk_idx]) {
+                (Some((before_dist, before)), Some((after_dist, after))) => {
+                    match before_dist.cmp(&after_dist) {
+                        cmp::Ordering::Less => before,
+                        cmp::Ordering::Greater => after,
+                        cmp::Ordering::Equal => (before + after) / 2.0,
+                    }
+                }
+                (Some((_, before)), None) => before,
+                (None, Some((_, after))) => after,
+                (None, None) => continue,
+            };
+            self.chunk_estimates.insert(chunk_idx, estimate);
+        }
+        self.rebuild_cumulative_sizes();
         Ok(())
     }
 
-    fn update_cumulative_sizes(&mut self, from_chunk: usize, diff: f64) -> Result<(), String> {
-        for i in from_chunk..self.cumulative_sizes.len() {
-            self.cumulative_sizes[i] += diff;
+    // O(1) while no chunk is loaded and no hint, kind or chunk estimate
+    // applies, since every chunk then matches the uniform estimate.
+    // Otherwise O(chunks + hints + kinds): an unloaded chunk only differs
+    // from its chunk estimate at hinted or kinded items.
+    fn rebuild_cumulative_sizes(&mut self) {
+        self.dynamic_estimate = self.dynamic_estimated_size();
+        self.rebuild_chunk_offsets();
+    }
+
+    // `rebuild_cumulative_sizes` keeping the applied dynamic estimate.
+    fn rebuild_chunk_offsets(&mut self) {
+        let base = self.base_estimate();
+        if self.chunk_access.is_empty()
+            && self.size_hints.is_empty()
+            && self.item_kinds.is_empty()
+            && self.chunk_estimates.is_empty()
+        {
+            self.chunk_offsets = ChunkOffsets::uniform(self.total_items, self.chunk_size, base);
+            self.total_size = self.total_items as f64 * base;
+            return;
+        }
+        let mut totals: Vec<f64> = (0..self.num_chunks())
+            .map(|chunk_idx| match self.chunks.get(&chunk_idx) {
+                Some(chunk) => chunk.total_size,
+                None => self.items_in_chunk(chunk_idx) as f64 * self.chunk_estimate(chunk_idx),
+            })
+            .collect();
+        for (index, size) in self.estimate_overrides() {
+            let chunk_idx = index / self.chunk_size;
+            if !self.chunks.contains_key(&chunk_idx) {
+                totals[chunk_idx] += size - self.chunk_estimate(chunk_idx);
+            }
+        }
+        self.chunk_offsets =
+            ChunkOffsets::with_totals(self.total_items, self.chunk_size, base, &totals);
+        self.total_size = totals.iter().sum();
+    }
+
+    fn estimated_item_size(&self, index: usize) -> f64 {
+        if let Some(&size) = self.size_hints.get(&index) {
+            return size;
+        }
+        if let Some(&size) = self
+            .item_kinds
+            .get(&index)
+            .and_then(|kind| self.kind_estimates.get(kind))
+        {
+            return size;
+        }
+        self.chunk_estimate(index / self.chunk_size)
+    }
+
+    // Estimate for items in `chunk_idx` without a hint or kind.
+    fn chunk_estimate(&self, chunk_idx: usize) -> f64 {
+        self.chunk_estimates
+            .get(&chunk_idx)
+            .copied()
+            .unwrap_or_else(|| self.base_estimate())
+    }
+
+    // Items whose estimate comes from a size hint or kind rather than their
+    // chunk, with that estimate.
+    fn estimate_overrides(&self) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let hinted = self.size_hints.iter().map(|(&index, &size)| (index, size));
+        let kinded = self
+            .item_kinds
+            .iter()
+            .filter(|(index, _)| !self.size_hints.contains_key(index))
+            .filter_map(|(&index, kind)| Some((index, *self.kind_estimates.get(kind)?)));
+        hinted.chain(kinded)
+    }
+
+    // Shifts the offsets after the estimate of item `index` changed from
+    // `old_size`. Loaded chunks keep their sizes, so only unloaded ones move.
+    fn estimate_changed(&mut self, index: usize, old_size: f64) {
+        let chunk_idx = index / self.chunk_size;
+        if !self.chunks.contains_key(&chunk_idx) {
+            let diff = self.estimated_item_size(index) - old_size;
+            self.chunk_offsets.add(chunk_idx, diff);
+            self.total_size += diff;
+        }
+    }
+
+    fn base_estimate(&self) -> f64 {
+        if self.config.use_dynamic_estimate {
+            self.dynamic_estimate
+        } else {
+            self.estimated_size
+        }
+    }
+
+    fn estimated_range_total(&self, start: usize, end: usize) -> f64 {
+        if self.size_hints.is_empty()
+            && self.item_kinds.is_empty()
+            && self.chunk_estimates.is_empty()
+        {
+            return (end - start) as f64 * self.base_estimate();
+        }
+        (start..end).map(|i| self.estimated_item_size(i)).sum()
+    }
+
+    fn estimated_chunk_total(&self, chunk_idx: usize) -> f64 {
+        let chunk_start = chunk_idx * self.chunk_size;
+        self.estimated_range_total(chunk_start, chunk_start + self.items_in_chunk(chunk_idx))
+    }
+
+    // How far chunk `chunk_idx` is from its uniform total. An unloaded
+    // chunk at the plain estimate counts as exactly uniform, even where the
+    // uniform totals of a huge list are rounded.
+    fn chunk_deviation(&self, chunk_idx: usize) -> f64 {
+        let total = match self.chunks.get(&chunk_idx) {
+            Some(chunk) => chunk.total_size,
+            None => {
+                let total = self.estimated_chunk_total(chunk_idx);
+                if total == self.items_in_chunk(chunk_idx) as f64 * self.base_estimate() {
+                    return 0.0;
+                }
+                total
+            }
+        };
+        total - self.chunk_offsets.uniform_total(chunk_idx)
+    }
+
+    fn num_chunks(&self) -> usize {
+        self.total_items.div_ceil(self.chunk_size)
+    }
+
+    // `chunk_size`, except for a partial last chunk. Zero for chunks past the
+    // end, including every chunk of an empty list.
+    fn items_in_chunk(&self, chunk_idx: usize) -> usize {
+        self.chunk_size
+            .min(self.total_items.saturating_sub(chunk_idx * self.chunk_size))
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct VisibleCellRange {
+    row_start: usize,
+    row_end: usize,
+    col_start: usize,
+    col_end: usize,
+    row_start_offset: f64,
+    row_end_offset: f64,
+    col_start_offset: f64,
+    col_end_offset: f64,
+}
+
+#[wasm_bindgen]
+impl VisibleCellRange {
+    #[wasm_bindgen(getter)]
+    pub fn row_start(&self) -> usize {
+        self.row_start
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn row_end(&self) -> usize {
+        self.row_end
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn col_start(&self) -> usize {
+        self.col_start
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn col_end(&self) -> usize {
+        self.col_end
+    }
+
+    /// Top edge of row `row_start`.
+    #[wasm_bindgen(getter)]
+    pub fn row_start_offset(&self) -> f64 {
+        self.row_start_offset
+    }
+
+    /// Bottom edge of row `row_end - 1`.
+    #[wasm_bindgen(getter)]
+    pub fn row_end_offset(&self) -> f64 {
+        self.row_end_offset
+    }
+
+    /// Left edge of column `col_start`.
+    #[wasm_bindgen(getter)]
+    pub fn col_start_offset(&self) -> f64 {
+        self.col_start_offset
+    }
+
+    /// Right edge of column `col_end - 1`.
+    #[wasm_bindgen(getter)]
+    pub fn col_end_offset(&self) -> f64 {
+        self.col_end_offset
+    }
+}
+
+/// A grid with variable row heights and column widths, modelled as one
+/// `VirtualList` per axis sharing the same config.
+#[wasm_bindgen]
+pub struct VirtualGrid {
+    rows: VirtualList,
+    columns: VirtualList,
+}
+
+#[wasm_bindgen]
+impl VirtualGrid {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        row_count: usize,
+        column_count: usize,
+        chunk_size: usize,
+        estimated_row_size: f64,
+        estimated_column_size: f64,
+        config: VirtualListConfig,
+    ) -> Result<VirtualGrid, VirtualListError> {
+        let orientation = Orientation::Vertical;
+        let rows = VirtualList::new(
+            row_count,
+            chunk_size,
+            estimated_row_size,
+            orientation,
+            config.clone(),
+        )?;
+        let columns = VirtualList::new(
+            column_count,
+            chunk_size,
+            estimated_column_size,
+            orientation.cross(),
+            config,
+        )?;
+        Ok(VirtualGrid { rows, columns })
+    }
+
+    #[wasm_bindgen]
+    pub fn update_row_size(&mut self, index: usize, new_size: f64) -> Result<(), VirtualListError> {
+        self.rows.update_item_size(index, new_size)
+    }
+
+    #[wasm_bindgen]
+    pub fn update_column_size(
+        &mut self,
+        index: usize,
+        new_size: f64,
+    ) -> Result<(), VirtualListError> {
+        self.columns.update_item_size(index, new_size)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_row_count(&mut self, count: usize) -> Result<(), VirtualListError> {
+        self.rows.set_total_items(count)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_column_count(&mut self, count: usize) -> Result<(), VirtualListError> {
+        self.columns.set_total_items(count)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_width(&self) -> f64 {
+        self.columns.total_size()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_height(&self) -> f64 {
+        self.rows.total_size()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_visible_cells(
+        &mut self,
+        scroll_x: f64,
+        scroll_y: f64,
+        viewport_width: f64,
+        viewport_height: f64,
+    ) -> Result<VisibleCellRange, VirtualListError> {
+        let rows = self.rows.get_visible_range(scroll_y, viewport_height)?;
+        let columns = self.columns.get_visible_range(scroll_x, viewport_width)?;
+        Ok(VisibleCellRange {
+            row_start: rows.start,
+            row_end: rows.end,
+            col_start: columns.start,
+            col_end: columns.end,
+            row_start_offset: rows.start_offset,
+            row_end_offset: rows.end_offset,
+            col_start_offset: columns.start_offset,
+            col_end_offset: columns.end_offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(total_items: usize, chunk_size: usize, estimated_size: f64) -> VirtualList {
+        list_with(
+            total_items,
+            chunk_size,
+            estimated_size,
+            VirtualListConfig::new(),
+        )
+    }
+
+    fn list_with(
+        total_items: usize,
+        chunk_size: usize,
+        estimated_size: f64,
+        config: VirtualListConfig,
+    ) -> VirtualList {
+        ok(VirtualList::new(
+            total_items,
+            chunk_size,
+            estimated_size,
+            Orientation::Vertical,
+            config,
+        ))
+    }
+
+    fn ok<T>(result: Result<T, VirtualListError>) -> T {
+        result.unwrap_or_else(|error| panic!("call failed: {:?}", error))
+    }
+
+    // Kind of the error `result` failed with.
+    fn error_kind<T>(result: Result<T, VirtualListError>) -> VirtualListErrorKind {
+        match result {
+            Err(VirtualListError::List { kind, .. }) => kind,
+            Err(error) => panic!("expected a list error, got {:?}", error),
+            Ok(_) => panic!("expected an error"),
         }
-        self.total_size += diff;
-        Ok(())
     }
 
-    #[wasm_bindgen]
-    pub fn get_visible_range(
-        &mut self,
-        scroll_position: f64,
-        viewport_size: f64,
-    ) -> Result<VisibleRange, JsValue> {
-        if viewport_size <= 0.0 {
-            return Err(convert_error(
-                "InvalidViewport",
-                "Viewport size must be positive",
-            ));
+    fn positions(list: &mut VirtualList) -> Vec<f64> {
+        (0..list.total_items())
+            .map(|index| list.get_position(index))
+            .collect()
+    }
+
+    // Deterministic LCG for shuffled indices and sizes in tests
+    fn next_random(state: &mut u64) -> u64 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *state >> 33
+    }
+
+    #[test]
+    fn rejected_batch_leaves_sizes_untouched() {
+        let mut config = VirtualListConfig::new();
+        config.set_update_batch_size(1);
+        let mut list = list_with(100, 10, 10.0, config);
+        let before = positions(&mut list);
+        let updates = [(3, 50.0), (45, 20.0), (72, -5.0), (90, f64::INFINITY)];
+        assert_eq!(
+            error_kind(list.apply_size_updates_in_batches(&updates)),
+            VirtualListErrorKind::InvalidSize
+        );
+        assert_eq!(positions(&mut list), before);
+        assert_eq!(list.total_size(), 1000.0);
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn batches_of_update_batch_size_match_one_pass() {
+        let updates: Vec<(usize, f64)> = (0..40).map(|k| (k * 7 % 100, k as f64)).collect();
+        let mut batched_config = VirtualListConfig::new();
+        batched_config.set_update_batch_size(3);
+        let mut batched = list_with(100, 10, 10.0, batched_config);
+        let mut whole_config = VirtualListConfig::new();
+        whole_config.set_update_batch_size(1000);
+        let mut whole = list_with(100, 10, 10.0, whole_config);
+        ok(batched.apply_size_updates_in_batches(&updates));
+        ok(whole.apply_size_updates_in_batches(&updates));
+        assert_eq!(positions(&mut batched), positions(&mut whole));
+        assert!(batched.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn import_with_an_invalid_size_changes_nothing() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_size(12, 30.0));
+        let before = positions(&mut list);
+        let json = r#"{"chunk_size":10,"total_items":100,"sizes":{"3":50.0,"41":1.0,"75":-5.0}}"#;
+        assert_eq!(
+            error_kind(list.import_sizes_json(json)),
+            VirtualListErrorKind::InvalidSize
+        );
+        assert!(list.verify_integrity().is_ok());
+        assert_eq!(list.total_size(), 1020.0);
+        assert_eq!(positions(&mut list), before);
+        assert!(!ok(list.is_item_measured(3)));
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let mut source = list(100, 10, 10.0);
+        ok(source.update_item_sizes(&[3, 41, 99], &[50.0, 1.0, 12.5]));
+        let mut target = list(100, 10, 10.0);
+        ok(target.import_sizes_json(&source.export_sizes_json()));
+        assert_eq!(positions(&mut target), positions(&mut source));
+        assert_eq!(target.total_size(), source.total_size());
+    }
+
+    #[test]
+    fn non_finite_sizes_are_rejected_and_exports_round_trip() {
+        let mut source = list(100, 10, 10.0);
+        ok(source.update_item_size(12, 30.0));
+        for size in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            assert_eq!(
+                error_kind(source.update_item_size(3, size)),
+                VirtualListErrorKind::InvalidSize
+            );
+            assert_eq!(
+                error_kind(source.update_item_sizes(&[4], &[size])),
+                VirtualListErrorKind::InvalidSize
+            );
         }
-        if self.total_items == 0 {
-            return Err(convert_error("EmptyList", "List is empty"));
+        ok(source.update_item_size(5, f64::MAX));
+        assert_eq!(
+            error_kind(source.update_item_size_relative(5, f64::MAX)),
+            VirtualListErrorKind::InvalidSize
+        );
+        assert!(!ok(source.is_item_measured(3)));
+        let mut target = list(100, 10, 10.0);
+        ok(target.import_sizes_json(&source.export_sizes_json()));
+        assert_eq!(positions(&mut target), positions(&mut source));
+        assert_eq!(target.total_size(), source.total_size());
+    }
+
+    #[test]
+    fn state_round_trip_preserves_layout_exactly() {
+        let mut config = VirtualListConfig::new();
+        config.set_use_dynamic_estimate(true);
+        let mut source = list_with(100, 10, 10.0, config.clone());
+        ok(source.set_item_size_hint(5, 40.0));
+        ok(source.set_item_kind_estimates(&[1], &[25.0]));
+        ok(source.assign_item_kinds(&[21, 22, 23], &[1, 1, 1]));
+        ok(source.update_item_sizes(&[3, 51], &[30.0, 14.0]));
+        // Loaded at the current dynamic estimate, which then moves on.
+        ok(source.get_visible_range(800.0, 50.0));
+        ok(source.update_item_size(52, 7.0));
+        ok(source.propagate_estimates());
+
+        let restored = ok(VirtualList::deserialize_state(
+            &source.serialize_state(),
+            config,
+        ));
+        let mut restored = restored;
+        assert!(restored.verify_integrity().is_ok());
+        assert_eq!(restored.total_size(), source.total_size());
+        assert_eq!(positions(&mut restored), positions(&mut source));
+        assert_eq!(restored.measured_indices(), source.measured_indices());
+    }
+
+    #[test]
+    fn dynamic_estimate_follows_the_mean_past_a_threshold() {
+        let mut config = VirtualListConfig::new();
+        config.set_use_dynamic_estimate(true);
+        let mut list = list_with(1000, 10, 10.0, config.clone());
+        ok(list.update_item_sizes(&(0..100).collect::<Vec<_>>(), &[10.0; 100]));
+        assert_eq!(list.get_position(500), 5000.0);
+        // 100 sizes at 10 and one at 15 move the mean by about 0.5%
+        ok(list.update_item_size(100, 15.0));
+        assert!(list.dynamic_estimated_size() > 10.0);
+        assert_eq!(list.get_position(500), 5005.0);
+        assert!(list.verify_integrity().is_ok());
+
+        // Restoring keeps the applied estimate rather than the newer mean
+        let mut restored = ok(VirtualList::deserialize_state(
+            &list.serialize_state(),
+            config,
+        ));
+        assert_eq!(positions(&mut restored), positions(&mut list.snapshot()));
+
+        // Another outlier takes the drift past 1% and re-estimates the rest
+        ok(list.update_item_size(101, 20.0));
+        let mean = list.dynamic_estimated_size();
+        // Chunk 10 is loaded and keeps its sizes; chunks 11 to 49 move
+        let expected = 1000.0 + 15.0 + 20.0 + 80.0 + 390.0 * mean;
+        assert!((list.get_position(500) - expected).abs() < 1e-9);
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn state_with_huge_chunk_count_is_rejected() {
+        let mut bytes = vec![STATE_FORMAT_VERSION];
+        bytes.extend_from_slice(&(1u64 << 60).to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&10.0f64.to_le_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&[0; 24]);
+        bytes.extend_from_slice(&[0; 48]);
+        assert_eq!(
+            error_kind(VirtualList::deserialize_state(
+                &bytes,
+                VirtualListConfig::new()
+            )),
+            VirtualListErrorKind::InvalidState
+        );
+    }
+
+    #[test]
+    fn hysteresis_is_measured_from_the_boundary() {
+        let mut config = VirtualListConfig::new();
+        config.set_boundary_hysteresis(5.0);
+        let mut list = list_with(100, 10, 10.0, config);
+        let mut held = ok(list.get_visible_range(50.0, 50.0));
+        let mut changes = Vec::new();
+        for scroll in 51..=200 {
+            let range = ok(list.get_visible_range(scroll as f64, 50.0));
+            if (range.start, range.end) != (held.start, held.end) {
+                changes.push(scroll);
+            }
+            held = range;
         }
-        let scroll_position = scroll_position.max(0.0).min(self.total_size);
-        let end_position = (scroll_position + viewport_size).min(self.total_size);
-        let (start_idx, start_offset) = self
-            .find_item_at_position(scroll_position)
-            .map_err(|e| convert_error("PositionError", &e))?;
-        let (end_idx, end_offset) = self
-            .find_item_at_position(end_position)
-            .map_err(|e| convert_error("PositionError", &e))?;
-        let buffer = self.config.buffer_size;
-        let overscan = self.config.overscan_items;
-        let start = start_idx.saturating_sub(buffer + overscan);
-        let end = (end_idx + buffer + overscan + 1).min(self.total_items);
-        Ok(VisibleRange {
-            start,
-            end,
-            start_offset,
-            end_offset,
-        })
+        assert_eq!(changes, (65..=195).step_by(10).collect::<Vec<_>>());
+
+        // Jitter back across the boundary it just passed keeps the range
+        let range = ok(list.get_visible_range(196.0, 50.0));
+        assert_eq!((range.start, range.end), (held.start, held.end));
+        let range = ok(list.get_visible_range(189.0, 50.0));
+        assert_eq!((range.start, range.end), (held.start, held.end));
     }
 
-    fn find_item_at_position(&mut self, position: f64) -> Result<(usize, f64), String> {
-        if self.total_items == 0 {
-            return Ok((0, 0.0));
+    #[test]
+    fn viewport_taller_than_the_list_returns_every_item() {
+        let mut list = list(3, 10, 20.0);
+        for scroll in [0.0, 30.0, 5000.0] {
+            let range = ok(list.get_visible_range(scroll, 10000.0));
+            assert_eq!((range.start, range.end), (0, 3));
+            assert_eq!(
+                (range.first_fully_visible, range.last_fully_visible),
+                (0, 2)
+            );
+            assert_eq!((range.start_offset, range.end_offset), (0.0, 60.0));
+            let range = ok(list.get_visible_range_cached(scroll, 10000.0));
+            assert_eq!((range.start, range.end), (0, 3));
+            let detailed = ok(list.get_visible_range_detailed(scroll, 10000.0));
+            assert_eq!((detailed.visible_start, detailed.visible_end), (0, 3));
+            assert_eq!((detailed.render_start, detailed.render_end), (0, 3));
+            let items = ok(list.compute_visible_range(scroll, 10000.0, 0));
+            assert_eq!(
+                items.iter().map(|item| item.index).collect::<Vec<_>>(),
+                vec![0, 1, 2]
+            );
         }
-        let chunk_idx = self
-            .cumulative_sizes
-            .binary_search_by(|&sum| sum.partial_cmp(&position).unwrap_or(cmp::Ordering::Greater))
-            .unwrap_or_else(|e| e - 1);
-        let chunk_start = if chunk_idx == 0 {
-            0.0
-        } else {
-            self.cumulative_sizes[chunk_idx - 1]
+    }
+
+    #[test]
+    fn shrinking_into_a_measured_chunk_trims_it() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_sizes(&[41, 44, 47], &[30.0, 20.0, 50.0]));
+        ok(list.set_total_items(45));
+        assert_eq!(list.chunks[&4].len(), 5);
+        assert_eq!(list.total_size(), 450.0 + 20.0 + 10.0);
+        assert_eq!(
+            error_kind(list.update_item_size(47, 5.0)),
+            VirtualListErrorKind::IndexOutOfBounds
+        );
+        assert!(list.verify_integrity().is_ok());
+        ok(list.update_item_size(43, 0.0));
+        assert!(list.verify_integrity().is_ok());
+        assert_eq!(list.get_position(44), 440.0 + 20.0 - 10.0);
+        assert_eq!(list.total_size(), 450.0 + 20.0);
+    }
+
+    #[test]
+    fn scrolling_to_or_past_the_end_keeps_the_last_screen() {
+        let mut list = list(100, 10, 10.0);
+        for scroll in [1000.0, 1500.0, f64::INFINITY] {
+            let range = ok(list.get_visible_range_exact(scroll, 100.0));
+            assert_eq!((range.start, range.end), (90, 100));
+        }
+        let mut config = VirtualListConfig::new();
+        config.set_reverse(true);
+        let mut reversed = list_with(100, 10, 10.0, config);
+        let range = ok(reversed.get_visible_range_exact(1000.0, 100.0));
+        assert_eq!((range.start, range.end), (0, 10));
+    }
+
+    #[test]
+    fn incremental_estimates_match_a_full_rebuild() {
+        let mut list = list(95, 10, 10.0);
+        ok(list.update_item_sizes(&[12, 47], &[30.0, 5.0]));
+        ok(list.propagate_estimates());
+        ok(list.set_item_size_hint(61, 40.0));
+        ok(list.set_item_size_hint(93, 2.0));
+        ok(list.assign_item_kinds(&[33, 61, 88, 89], &[1, 1, 2, 1]));
+        ok(list.set_item_kind_estimates(&[1, 2], &[25.0, 14.0]));
+        ok(list.assign_item_kinds(&[34], &[2]));
+        ok(list.set_item_kind_estimates(&[1], &[18.0]));
+        let check = |list: &mut VirtualList| {
+            let mut rebuilt = list.clone();
+            rebuilt.rebuild_cumulative_sizes();
+            assert_eq!(positions(list), positions(&mut rebuilt));
+            assert_eq!(list.total_size(), rebuilt.total_size());
+            assert!(list.verify_integrity().is_ok());
         };
-        let position_in_chunk = position - chunk_start;
-        let chunk = self
-            .get_or_create_chunk(chunk_idx)
-            .map_err(|e| format!("{:?}", e))?;
-        let (item_idx, offset) = chunk.find_item_at_position(position_in_chunk)?;
-        let global_idx = chunk_idx * self.chunk_size + item_idx;
-        Ok((global_idx.min(self.total_items - 1), offset))
+        check(&mut list);
+        for total in [95, 91, 89, 40, 47, 300, 1000, 999, 1005, 0, 23] {
+            ok(list.set_total_items(total));
+            check(&mut list);
+        }
+        ok(list.append_items(7));
+        assert_eq!(list.chunk_offsets.len(), 3);
+        check(&mut list);
     }
 
-    #[wasm_bindgen]
-    pub fn batch_update_sizes(&mut self, updates: Vec<JsValue>) -> Result<(), JsValue> {
-        let parsed_updates: Vec<Result<(usize, f64), String>> = updates
-            .into_iter()
-            .map(|js_val| {
-                let arr = js_val
-                    .dyn_into::<Array>()
-                    .map_err(|_| "Invalid update format".to_string())?;
-                if arr.length() != 2 {
-                    return Err("Each update must be an array of [index, size]".to_string());
-                }
-                let index =
-                    arr.get(0)
-                        .as_f64()
-                        .ok_or("Index must be a number".to_string())? as usize;
-                let size = arr
-                    .get(1)
-                    .as_f64()
-                    .ok_or("Size must be a number".to_string())?;
-                Ok((index, size))
-            })
-            .collect();
+    #[test]
+    fn chunk_estimates_cover_partial_full_and_empty_lists() {
+        let partial = list(7, 10, 10.0);
+        assert_eq!(partial.estimated_chunk_total(0), 70.0);
+        let full = list(30, 10, 10.0);
+        assert_eq!(full.estimated_chunk_total(2), 100.0);
+        assert_eq!(full.estimated_chunk_total(3), 0.0);
 
-        let updates: Vec<(usize, f64)> = parsed_updates
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| convert_error("InvalidUpdate", &e))?;
+        let mut empty = list(0, 10, 10.0);
+        assert_eq!(empty.estimated_chunk_total(0), 0.0);
+        assert_eq!(empty.total_size(), 0.0);
+        assert_eq!(
+            error_kind(empty.get_visible_range(0.0, 100.0)),
+            VirtualListErrorKind::EmptyList
+        );
+        assert_eq!(empty.find_item_at_position(50.0), Ok((0, 0.0)));
+        assert_eq!(empty.render_bounds(0, 0, (0.0, 100.0), 0.0), (0, 0));
+        assert!(empty.verify_integrity().is_ok());
+    }
 
-        let mut chunk_updates: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
-        for (index, new_size) in updates {
-            if index >= self.total_items {
-                return Err(convert_error(
-                    "IndexOutOfBounds",
-                    &format!("Index {} out of bounds", index),
-                ));
-            }
-            let chunk_idx = index / self.chunk_size;
-            let item_idx = index % self.chunk_size;
-            chunk_updates
-                .entry(chunk_idx)
-                .or_insert_with(Vec::new)
-                .push((item_idx, new_size));
+    #[test]
+    fn prefix_search_returns_the_first_tied_edge() {
+        let mut list = list(100, 8, 10.0);
+        // Zero-sized runs at the start, across several chunks and at the end
+        let zeros: Vec<u32> = (0..3).chain(20..45).chain(97..100).collect();
+        ok(list.update_item_sizes(&zeros, &vec![0.0; zeros.len()]));
+        let edges = positions(&mut list);
+        let lower_bound = |target: f64| edges.iter().position(|&edge| edge >= target);
+
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(0.0), 0);
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(170.0), 20);
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(170.5), 46);
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(10.0), 4);
+        assert_eq!(
+            list.find_smallest_i_where_prefix_sum_ge(list.total_size()),
+            97
+        );
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(1e9), 100);
+        for step in 0..=(list.total_size() as usize * 2 + 4) {
+            let target = step as f64 / 2.0;
+            assert_eq!(
+                list.find_smallest_i_where_prefix_sum_ge(target),
+                lower_bound(target).unwrap_or(100),
+                "target {}",
+                target
+            );
         }
+    }
 
-        let mut chunk_diffs: HashMap<usize, f64> = HashMap::new();
-        for (chunk_idx, updates) in chunk_updates {
-            let chunk = self
-                .get_or_create_chunk(chunk_idx)
-                .map_err(|e| convert_error("ChunkError", &format!("{:?}", e)))?;
-            let mut total_diff = 0.0;
-            for (item_idx, new_size) in updates {
-                let diff = chunk
-                    .update_size(item_idx, new_size)
-                    .map_err(|e| convert_error("UpdateError", &e))?;
-                total_diff += diff;
+    #[test]
+    fn propagated_estimates_follow_measured_neighbours() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_sizes(&(30..40).collect::<Vec<_>>(), &[50.0; 10]));
+        ok(list.unload_chunk(0));
+        ok(list.propagate_estimates());
+        assert_eq!(list.estimated_chunk_total(2), 500.0);
+        assert_eq!(list.estimated_chunk_total(9), 500.0);
+        assert_eq!(list.total_size(), 100.0 * 50.0);
+        assert!(list.verify_integrity().is_ok());
+        // Materialized chunks start from the propagated estimate
+        let scroll = list.get_position(80);
+        ok(list.get_visible_range(scroll, 20.0));
+        assert_eq!(ok(list.item_size(80)), 50.0);
+    }
+
+    #[test]
+    fn fill_state_reports_under_and_over_filled_viewports() {
+        let mut long = list(100, 10, 10.0);
+        assert!(ok(long.viewport_fill_state(300.0, 100.0)) >= 1.0);
+        assert!(ok(long.viewport_fill_state(1000.0, 100.0)) >= 1.0);
+        let mut short = list(3, 10, 10.0);
+        assert_eq!(ok(short.viewport_fill_state(0.0, 100.0)), 0.3);
+    }
+
+    #[test]
+    fn frozen_list_rejects_updates_but_answers_queries() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_size(5, 30.0));
+        list.freeze();
+        let generation = list.generation();
+        assert_eq!(
+            error_kind(list.update_item_size(5, 40.0)),
+            VirtualListErrorKind::Frozen
+        );
+        assert_eq!(
+            error_kind(list.update_item_sizes(&[1], &[20.0])),
+            VirtualListErrorKind::Frozen
+        );
+        assert_eq!(
+            error_kind(list.set_total_items(10)),
+            VirtualListErrorKind::Frozen
+        );
+        assert_eq!(
+            error_kind(list.unload_chunk(0)),
+            VirtualListErrorKind::Frozen
+        );
+        assert_eq!(list.generation(), generation);
+        // Queries may still materialize chunks
+        assert_eq!(list.get_position(50), 520.0);
+        let range = ok(list.get_visible_range(600.0, 100.0));
+        assert!(range.start <= 58 && range.end >= 70);
+        assert_eq!(list.total_size(), 1020.0);
+        list.unfreeze();
+        ok(list.update_item_size(5, 40.0));
+        assert_eq!(list.total_size(), 1030.0);
+    }
+
+    #[test]
+    fn min_item_size_floors_zero_sizes() {
+        let mut config = VirtualListConfig::new();
+        config.set_min_item_size(4.0);
+        let mut list = list_with(20, 5, 10.0, config);
+        let indices: Vec<u32> = (0..20).collect();
+        ok(list.update_item_sizes(&indices, &[0.0; 20]));
+        assert_eq!(list.total_size(), 80.0);
+        assert_eq!(list.get_position(7), 28.0);
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(30.0), 8);
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn batch_total_matches_a_recomputation() {
+        let mut config = VirtualListConfig::new();
+        config.set_update_batch_size(2);
+        let mut list = list_with(100, 10, 10.0, config);
+        // The highest changed chunk (5) is well before the last one
+        ok(list.update_item_sizes(&[52, 3, 31, 57, 8], &[40.0, 0.0, 15.0, 22.5, 11.0]));
+        let total = list.total_size();
+        assert_eq!(total, 1000.0 + 30.0 - 10.0 + 5.0 + 12.5 + 1.0);
+        assert_eq!(list.get_position(99) + 10.0, total);
+        ok(list.recompute());
+        assert_eq!(list.total_size(), total);
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn scrolling_to_the_exact_end_includes_the_last_item() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_size(99, 25.0));
+        let total = list.total_size();
+        assert_eq!(list.find_item_at_position(total), Ok((99, 25.0)));
+        let hit = ok(list.item_at_position(total));
+        assert_eq!((hit.index, hit.offset), (99, 25.0));
+        let range = ok(list.get_visible_range_exact(total - 100.0, 100.0));
+        assert_eq!(range.end, 100);
+        assert_eq!(range.last_fully_visible, 99);
+        assert_eq!(range.end_offset, total);
+        let range = ok(list.get_visible_range(total, 100.0));
+        assert_eq!(range.end, 100);
+    }
+
+    #[test]
+    fn every_query_handles_an_empty_list() {
+        let mut list = list(0, 10, 10.0);
+        assert_eq!(list.total_size(), 0.0);
+        assert!(list.cumulative_sizes().is_empty());
+        assert_eq!(ok(list.item_prefix_sums(true)), vec![0.0]);
+        assert!(!list.contains_position(0.0));
+        assert_eq!(list.get_position(0), 0.0);
+        assert_eq!(list.find_smallest_i_where_prefix_sum_ge(5.0), 0);
+        assert_eq!(list.max_scroll_offset(100.0), 0.0);
+        assert_eq!(list.clamp_scroll(50.0, 100.0), 0.0);
+        assert_eq!(ok(list.item_count_in_range(0.0, 100.0)), 0);
+        assert_eq!(ok(list.get_range_size(0, 0)), 0.0);
+        assert_eq!(list.measured_size_fraction(), 0.0);
+        assert!(list.measured_indices().is_empty());
+        assert_eq!(ok(list.size_histogram(10.0, 4)), vec![0; 4]);
+        for kind in [
+            error_kind(list.get_visible_range(0.0, 100.0)),
+            error_kind(list.get_visible_range_exact(0.0, 100.0)),
+            error_kind(list.get_visible_range_cached(0.0, 100.0)),
+            error_kind(list.get_visible_range_detailed(0.0, 100.0)),
+            error_kind(list.compute_visible_range(0.0, 100.0, 2)),
+            error_kind(list.item_at_position(0.0)),
+            error_kind(list.items_at_positions(&[0.0])),
+            error_kind(list.nearest_item_boundary(0.0)),
+        ] {
+            assert_eq!(kind, VirtualListErrorKind::EmptyList);
+        }
+        for kind in [
+            error_kind(list.checked_get_position(0)),
+            error_kind(list.get_item_bounds(0)),
+            error_kind(list.update_item_size(0, 5.0)),
+            error_kind(list.is_item_measured(0)),
+        ] {
+            assert_eq!(kind, VirtualListErrorKind::IndexOutOfBounds);
+        }
+        assert_eq!(
+            error_kind(list.unload_chunk(0)),
+            VirtualListErrorKind::InvalidChunkIndex
+        );
+        assert_eq!(
+            error_kind(list.is_chunk_loaded(0)),
+            VirtualListErrorKind::InvalidChunkIndex
+        );
+        ok(list.update_item_sizes(&[], &[]));
+        ok(list.propagate_estimates());
+        ok(list.recompute());
+        ok(list.set_chunk_size(4));
+        assert!(list.verify_integrity().is_ok());
+        assert!(!list.debug_state().is_empty());
+        let restored = ok(VirtualList::deserialize_state(
+            &list.serialize_state(),
+            VirtualListConfig::new(),
+        ));
+        assert_eq!(restored.total_items(), 0);
+        ok(list.set_total_items(3));
+        assert_eq!(list.total_size(), 30.0);
+    }
+
+    #[test]
+    fn lists_near_usize_max_do_not_overflow() {
+        let mut config = VirtualListConfig::new();
+        config.set_buffer_size(usize::MAX);
+        config.set_overscan_items(usize::MAX);
+        let mut list = list_with(usize::MAX, 7, 1.0, config);
+        assert_eq!(list.num_chunks(), usize::MAX / 7 + 1);
+        assert_eq!(list.items_in_chunk(usize::MAX / 7), usize::MAX % 7);
+        let last = usize::MAX - 1;
+        assert_eq!(list.get_position(last), last as f64);
+        assert_eq!(ok(list.checked_get_position(last)), last as f64);
+        let range = ok(list.get_visible_range_with_velocity(list.total_size(), 10.0, 5.0));
+        assert_eq!((range.start, range.end), (0, usize::MAX));
+        ok(list.set_total_items(10));
+        ok(list.set_total_items(usize::MAX));
+        assert_eq!(list.num_chunks(), usize::MAX / 7 + 1);
+        ok(list.set_chunk_size(usize::MAX));
+        assert_eq!(list.num_chunks(), 1);
+        assert_eq!(list.items_in_chunk(0), usize::MAX);
+    }
+
+    #[test]
+    fn inverted_axis_reads_scroll_from_the_end() {
+        let mut config = VirtualListConfig::new();
+        config.set_invert_axis(true);
+        let mut list = list_with(100, 10, 10.0, config);
+        for (scroll, expected) in [
+            (-50.0, (90, 100)),
+            (0.0, (90, 100)),
+            (450.0, (45, 55)),
+            (900.0, (0, 10)),
+            (5000.0, (0, 10)),
+        ] {
+            let range = ok(list.get_visible_range_exact(scroll, 100.0));
+            assert_eq!((range.start, range.end), expected, "scroll {}", scroll);
+            assert!(range.start_offset >= 0.0 && range.end_offset >= 0.0);
+            let range = ok(list.get_visible_range(scroll, 100.0));
+            assert!(range.start_offset >= 0.0 && range.end_offset >= 0.0);
+        }
+    }
+
+    #[test]
+    fn last_chunk_item_count_follows_every_resize() {
+        let mut list = list(25, 10, 10.0);
+        assert_eq!(list.items_in_chunk(2), 5);
+        ok(list.update_item_size(24, 20.0));
+        assert_eq!(list.chunks[&2].len(), 5);
+        ok(list.set_total_items(22));
+        assert_eq!(list.items_in_chunk(2), 2);
+        assert_eq!(list.chunks[&2].len(), 2);
+        ok(list.set_total_items(38));
+        assert_eq!(list.items_in_chunk(3), 8);
+        assert_eq!(list.chunks[&2].len(), 10);
+        assert_eq!(ok(list.append_items(2)), 400.0);
+        assert_eq!(list.items_in_chunk(3), 10);
+        assert_eq!(list.items_in_chunk(4), 0);
+        ok(list.set_chunk_size(7));
+        assert_eq!(list.items_in_chunk(5), 5);
+        ok(list.update_item_size(39, 5.0));
+        assert_eq!(list.chunks[&5].len(), 5);
+        ok(list.set_total_items(30));
+        assert_eq!(list.items_in_chunk(4), 2);
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn position_epsilon_snaps_lookups_near_1e8() {
+        let mut exact = list(2_000_000, 1000, 100.1);
+        let mut config = VirtualListConfig::new();
+        config.set_position_epsilon(1e-6);
+        let mut snapped = list_with(2_000_000, 1000, 100.1, config);
+        for index in [999_001, 999_500, 1_000_000, 1_500_123] {
+            let edge = exact.get_position(index);
+            assert!(edge > 1e8);
+            // A position computed slightly differently on the JS side
+            let short = edge - 1e-7;
+            assert_eq!(ok(exact.item_at_position(short)).index, index - 1);
+            assert_eq!(ok(snapped.item_at_position(short)).index, index);
+            assert_eq!(ok(snapped.item_at_position(edge)).index, index);
+            assert_eq!(ok(snapped.item_at_position(edge - 1e-3)).index, index - 1);
+        }
+    }
+
+    #[test]
+    fn fenwick_prefix_sums_match_a_naive_sum() {
+        let mut state = 332;
+        for len in [1, 2, 7, 64, 1000] {
+            let mut naive: Vec<f64> = (0..len)
+                .map(|_| (next_random(&mut state) % 50) as f64)
+                .collect();
+            let mut dense = DenseSizes::new(naive.clone());
+            for _ in 0..200 {
+                let index = next_random(&mut state) as usize % len;
+                let size = (next_random(&mut state) % 80) as f64 / 4.0;
+                assert_eq!(dense.set(index, size), naive[index]);
+                naive[index] = size;
+                let count = next_random(&mut state) as usize % (len + 1);
+                assert_eq!(dense.prefix(count), naive[..count].iter().sum::<f64>());
+            }
+            let mut sum = 0.0;
+            for (index, &size) in naive.iter().enumerate() {
+                let (found, offset) = dense.find(sum + size / 2.0, |_| 0.0);
+                if size > 0.0 {
+                    assert_eq!((found, offset), (index, size / 2.0));
+                }
+                sum += size;
             }
-            chunk_diffs.insert(chunk_idx, total_diff);
         }
+    }
 
-        let min_chunk_idx = chunk_diffs.keys().min().cloned().unwrap_or(0);
-        let mut cumulative_diff = 0.0;
-        for i in min_chunk_idx..self.chunks.len() {
-            if let Some(diff) = chunk_diffs.get(&i) {
-                cumulative_diff += diff;
+    // cargo test --release -- --ignored --nocapture bench_update_sizes
+    #[test]
+    #[ignore]
+    fn bench_update_sizes() {
+        use std::time::Instant;
+        const UPDATES: usize = 100_000;
+        for chunk_size in [256, 1024, 4096, 16384] {
+            let mut state = 7;
+            let ops: Vec<(usize, f64)> = (0..UPDATES)
+                .map(|_| {
+                    let index = next_random(&mut state) as usize % chunk_size;
+                    (index, (next_random(&mut state) % 100) as f64)
+                })
+                .collect();
+
+            // The previous layout: running sums shifted on every update
+            let mut sizes = vec![10.0; chunk_size];
+            let mut prefix: Vec<f64> = (0..=chunk_size).map(|i| i as f64 * 10.0).collect();
+            let started = Instant::now();
+            let mut checksum = 0.0;
+            for &(index, size) in &ops {
+                let diff = size - std::mem::replace(&mut sizes[index], size);
+                for sum in &mut prefix[index + 1..] {
+                    *sum += diff;
+                }
+                checksum += prefix[chunk_size / 2];
             }
-            if i < self.cumulative_sizes.len() {
-                self.cumulative_sizes[i] += cumulative_diff;
+            let linear = started.elapsed();
+
+            let mut dense = DenseSizes::new(vec![10.0; chunk_size]);
+            let started = Instant::now();
+            let mut fenwick_checksum = 0.0;
+            for &(index, size) in &ops {
+                dense.set(index, size);
+                fenwick_checksum += dense.prefix(chunk_size / 2);
             }
+            let fenwick = started.elapsed();
+
+            assert_eq!(checksum, fenwick_checksum);
+            println!(
+                "chunk_size {:>5}: linear {:>9.2?}  fenwick {:>9.2?}  ({:.1}x)",
+                chunk_size,
+                linear,
+                fenwick,
+                linear.as_secs_f64() / fenwick.as_secs_f64()
+            );
         }
-        self.total_size += cumulative_diff;
-        Ok(())
     }
 
-    #[wasm_bindgen]
-    pub fn set_total_items(&mut self, new_total: usize) -> Result<(), JsValue> {
-        if new_total == self.total_items {
-            return Ok(());
+    // CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner
+    //     cargo test --target wasm32-unknown-unknown
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn errors_reach_js_as_error_objects() {
+        let mut list = list(10, 5, 10.0);
+        let error = JsValue::from(list.update_item_size(3, -1.0).unwrap_err());
+        let field = |name: &str| js_sys::Reflect::get(&error, &JsValue::from_str(name)).unwrap();
+        assert_eq!(field("kind"), "InvalidSize");
+        assert_eq!(field("code"), VirtualListErrorKind::InvalidSize as u32);
+        assert_eq!(field("message"), "Invalid size: -1");
+
+        let thrown = JsValue::from_str("from a callback");
+        let error = JsValue::from(VirtualListError::from(thrown.clone()));
+        assert_eq!(error, thrown);
+    }
+
+    // RUSTFLAGS="-C target-feature=+simd128" CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner
+    //     cargo test --target wasm32-unknown-unknown
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn simd_running_sums_match_scalar() {
+        let mut state = 331;
+        for len in [0, 1, 2, 7, 256, 1025] {
+            let sizes: Vec<f64> = (0..len)
+                .map(|_| (next_random(&mut state) % 1000) as f64 / 8.0)
+                .collect();
+            let (mut simd, mut scalar) = (Vec::new(), Vec::new());
+            push_running_sums_simd(&sizes, 12.5, &mut simd);
+            push_running_sums_scalar(&sizes, 12.5, &mut scalar);
+            assert_eq!(simd.len(), len);
+            for (a, b) in simd.iter().zip(&scalar) {
+                assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{a} != {b}");
+            }
         }
-        let new_num_chunks = if new_total == 0 {
-            0
-        } else {
-            (new_total + self.chunk_size - 1) / self.chunk_size
-        };
-        let old_num_chunks = self.chunks.len();
+    }
 
-        if new_num_chunks > old_num_chunks {
-            self.chunks.resize_with(new_num_chunks, || None);
-            let mut last_cumulative = if old_num_chunks > 0 {
-                self.cumulative_sizes[old_num_chunks - 1]
-            } else {
-                0.0
+    // RUSTFLAGS="-C target-feature=+simd128" CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner
+    //     cargo test --release --target wasm32-unknown-unknown -- --include-ignored --nocapture bench_running_sums
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[ignore]
+    fn bench_running_sums() {
+        const ROUNDS: usize = 20_000;
+        type PushRunningSums = fn(&[f64], f64, &mut Vec<f64>);
+        for chunk_size in [256, 1024] {
+            let sizes: Vec<f64> = (0..chunk_size).map(|i| (i % 17) as f64 + 0.5).collect();
+            let mut out = Vec::with_capacity(chunk_size);
+            let mut time = |push: PushRunningSums| {
+                let started = js_sys::Date::now();
+                let mut checksum = 0.0;
+                for _ in 0..ROUNDS {
+                    out.clear();
+                    push(&sizes, 0.0, &mut out);
+                    checksum += out[chunk_size - 1];
+                }
+                (js_sys::Date::now() - started, checksum)
             };
-            for i in old_num_chunks..new_num_chunks {
-                let items_in_chunk = if i == new_num_chunks - 1 && new_total % self.chunk_size != 0
-                {
-                    new_total % self.chunk_size
-                } else {
-                    self.chunk_size
-                };
-                let chunk_total = items_in_chunk as f64 * self.estimated_size;
-                last_cumulative += chunk_total;
-                self.cumulative_sizes.push(last_cumulative);
-            }
-        } else if new_num_chunks < old_num_chunks {
-            self.chunks.truncate(new_num_chunks);
-            self.cumulative_sizes.truncate(new_num_chunks);
-            if new_num_chunks > 0 {
-                let last_chunk_idx = new_num_chunks - 1;
-                let items_in_last_chunk = if new_total % self.chunk_size == 0 {
-                    self.chunk_size
-                } else {
-                    new_total % self.chunk_size
-                };
-                let last_chunk_total = if let Some(chunk) = &self.chunks[last_chunk_idx] {
-                    chunk.sizes[..items_in_last_chunk].iter().sum::<f64>()
-                } else {
-                    items_in_last_chunk as f64 * self.estimated_size
-                };
-                if last_chunk_idx == 0 {
-                    self.cumulative_sizes[0] = last_chunk_total;
-                } else {
-                    self.cumulative_sizes[last_chunk_idx] =
-                        self.cumulative_sizes[last_chunk_idx - 1] + last_chunk_total;
+            let (scalar, scalar_checksum) = time(push_running_sums_scalar);
+            let (simd, simd_checksum) = time(push_running_sums_simd);
+            assert_eq!(scalar_checksum, simd_checksum);
+            wasm_bindgen_test::console_log!(
+                "chunk_size {:>4}: scalar {:>7.1}ms  simd {:>7.1}ms  ({:.2}x)",
+                chunk_size,
+                scalar,
+                simd,
+                scalar / simd
+            );
+        }
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_append_with_hints
+    #[test]
+    #[ignore]
+    fn bench_append_with_hints() {
+        use std::time::Instant;
+        for hinted in [false, true] {
+            let mut list = list(10_000_000, 100, 20.0);
+            if hinted {
+                ok(list.set_item_size_hint(5, 40.0));
+            }
+            let started = Instant::now();
+            for _ in 0..5 {
+                ok(list.append_items(1));
+            }
+            println!(
+                "10M items, hint {:>5}: 5 appends in {:>9.2?}",
+                hinted,
+                started.elapsed()
+            );
+            assert!(list.verify_integrity().is_ok());
+        }
+    }
+
+    #[test]
+    fn measured_runs_match_dense_flags() {
+        let mut state = 361;
+        let mut dense = MeasuredFlags::Dense(vec![false; 40]);
+        let mut runs = MeasuredFlags::Runs(MeasuredRuns::new(40));
+        for step in 0..500usize {
+            let len = dense.iter().count();
+            match next_random(&mut state) % 8 {
+                0 if step.is_multiple_of(50) => {
+                    let measured = next_random(&mut state).is_multiple_of(2);
+                    dense.fill(measured);
+                    runs.fill(measured);
                 }
-                self.total_size = self.cumulative_sizes[last_chunk_idx];
-            } else {
-                self.total_size = 0.0;
+                1 => {
+                    let keep = next_random(&mut state) as usize % (len + 1);
+                    let new_len = keep + next_random(&mut state) as usize % 10;
+                    dense.resize(keep, new_len);
+                    runs.resize(keep, new_len);
+                }
+                _ if len > 0 => {
+                    let index = next_random(&mut state) as usize % len;
+                    let measured = !next_random(&mut state).is_multiple_of(3);
+                    dense.set(index, measured);
+                    runs.set(index, measured);
+                }
+                _ => {}
             }
-        } else if new_total % self.chunk_size != 0 {
-            let last_chunk_idx = new_num_chunks - 1;
-            let items_in_last_chunk = new_total % self.chunk_size;
-            let last_chunk_total = if let Some(chunk) = &self.chunks[last_chunk_idx] {
-                chunk.sizes[..items_in_last_chunk].iter().sum::<f64>()
-            } else {
-                items_in_last_chunk as f64 * self.estimated_size
+            assert_eq!(
+                runs.iter().collect::<Vec<_>>(),
+                dense.iter().collect::<Vec<_>>()
+            );
+        }
+        if let MeasuredFlags::Runs(runs) = &runs {
+            assert!(runs.flips.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(runs.flips.iter().all(|&flip| flip < runs.len));
+        }
+    }
+
+    #[test]
+    fn run_length_chunks_track_measured_items() {
+        let mut config = VirtualListConfig::new();
+        config.set_run_length_chunks(true);
+        let mut list = list_with(1000, 500, 10.0, config);
+        ok(list.update_item_sizes(&[3, 4, 5, 700], &[10.0, 10.0, 20.0, 10.0]));
+        assert_eq!(list.measured_indices(), vec![3, 4, 5, 700]);
+        match &list.chunks[&0].measured {
+            MeasuredFlags::Runs(runs) => assert_eq!(runs.flips, vec![3, 6]),
+            MeasuredFlags::Dense(_) => panic!("expected measured runs"),
+        }
+        ok(list.set_total_items(5));
+        assert_eq!(list.measured_indices(), vec![3, 4]);
+        ok(list.set_total_items(800));
+        assert_eq!(list.measured_indices(), vec![3, 4]);
+        assert!(!ok(list.is_item_measured(5)));
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_run_length_chunks
+    #[test]
+    #[ignore]
+    fn bench_run_length_chunks() {
+        use std::mem::size_of;
+        use std::time::Instant;
+        fn heap_bytes(chunk: &Chunk) -> usize {
+            let sizes = match &chunk.sizes {
+                ChunkSizes::Dense(dense) => {
+                    (dense.sizes.capacity() + dense.tree.capacity()) * size_of::<f64>()
+                }
+                ChunkSizes::RunLength(rle) => {
+                    rle.runs.capacity() * size_of::<(usize, f64)>()
+                        + rle.run_sums.capacity() * size_of::<f64>()
+                }
             };
-            if last_chunk_idx == 0 {
-                self.cumulative_sizes[0] = last_chunk_total;
-            } else {
-                self.cumulative_sizes[last_chunk_idx] =
-                    self.cumulative_sizes[last_chunk_idx - 1] + last_chunk_total;
+            let measured = match &chunk.measured {
+                MeasuredFlags::Dense(flags) => flags.capacity(),
+                MeasuredFlags::Runs(runs) => runs.flips.capacity() * size_of::<usize>(),
+            };
+            sizes + measured
+        }
+        const QUERIES: usize = 200_000;
+        for (chunk_size, distinct) in [(1024, 4), (1024, 64), (8192, 4), (8192, 512)] {
+            let mut row = Vec::new();
+            for run_length in [false, true] {
+                let mut state = 9;
+                let mut chunk = Chunk::new(vec![24.0; chunk_size], run_length).unwrap();
+                for _ in 0..distinct {
+                    let index = next_random(&mut state) as usize % chunk_size;
+                    chunk.update_size(index, 48.0).unwrap();
+                }
+                let total = chunk.total_size;
+                let started = Instant::now();
+                let mut checksum = 0;
+                for query in 0..QUERIES {
+                    let position = total * query as f64 / QUERIES as f64;
+                    checksum += chunk.find_item_at_position(position, |_| 0.0).unwrap().0;
+                }
+                row.push((heap_bytes(&chunk), started.elapsed(), checksum));
             }
-            self.total_size = self.cumulative_sizes[last_chunk_idx];
+            assert_eq!(row[0].2, row[1].2);
+            println!(
+                "{:>4} items, {:>3} changed: dense {:>6} B {:>8.2?}  rle {:>6} B {:>8.2?}",
+                chunk_size, distinct, row[0].0, row[0].1, row[1].0, row[1].1
+            );
         }
-        self.total_items = new_total;
-        Ok(())
     }
 
-    #[wasm_bindgen]
-    pub fn unload_chunk(&mut self, chunk_idx: usize) -> Result<(), JsValue> {
-        if chunk_idx >= self.chunks.len() {
-            return Err(convert_error(
-                "InvalidChunkIndex",
-                &format!("Chunk index {} out of bounds", chunk_idx),
-            ));
+    #[test]
+    fn anchored_batch_follows_the_item_under_the_scroll() {
+        let mut config = VirtualListConfig::new();
+        config.set_use_dynamic_estimate(true);
+        let mut list = list_with(100, 10, 10.0, config.clone());
+        ok(list.get_visible_range(0.0, 30.0));
+        // Measuring 20px items raises the estimate of every unloaded chunk
+        let scroll = ok(list.batch_update_sizes_anchored(&[0, 1, 2, 3, 4], &[20.0; 5], 500.0));
+        assert_eq!(scroll, list.get_position(50));
+        assert!(scroll > 500.0 + 5.0 * 10.0);
+        // Part way into an item, the offset into it is kept
+        let scroll = ok(list.batch_update_sizes_anchored(&[2], &[40.0], scroll + 5.0));
+        assert_eq!(scroll, list.get_position(50) + 5.0);
+        // Nothing above the scroll position
+        assert_eq!(ok(list.batch_update_sizes_anchored(&[0], &[5.0], 0.0)), 0.0);
+
+        config.set_use_dynamic_estimate(false);
+        config.set_reverse(true);
+        let mut reversed = list_with(100, 10, 10.0, config);
+        // Scroll 0 shows item 99, so item 95 is below and 97 is on screen
+        let scroll = ok(reversed.batch_update_sizes_anchored(&[95, 97], &[30.0, 50.0], 25.0));
+        assert_eq!(scroll, 25.0 + 40.0);
+    }
+
+    #[test]
+    fn dispose_respects_freeze() {
+        let mut list = list(100, 10, 10.0);
+        ok(list.update_item_sizes(&[3, 42], &[20.0, 30.0]));
+        list.freeze();
+        assert_eq!(error_kind(list.dispose()), VirtualListErrorKind::Frozen);
+        assert_eq!(list.total_items(), 100);
+        assert_eq!(list.measured_indices(), vec![3, 42]);
+        list.unfreeze();
+        let generation = list.generation();
+        ok(list.dispose());
+        assert_eq!(list.generation(), generation + 1);
+        assert_eq!(list.total_items(), 0);
+        assert!(list.chunks.is_empty());
+        assert!(list.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn with_sizes_respects_max_loaded_chunks() {
+        let sizes: Vec<f64> = (0..100).map(|i| (i % 7) as f64 + 1.0).collect();
+        let mut config = VirtualListConfig::new();
+        config.set_max_loaded_chunks(Some(3));
+        let mut capped = ok(VirtualList::with_sizes(
+            &sizes,
+            10,
+            Orientation::Vertical,
+            config,
+        ));
+        assert_eq!(
+            capped.chunks.keys().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(capped.chunk_access.len(), 3);
+        let total: f64 = sizes.iter().sum();
+        assert_eq!(capped.total_size(), total);
+        for boundary in [30, 50, 90] {
+            let before: f64 = sizes[..boundary].iter().sum();
+            assert!((capped.get_position(boundary) - before).abs() < 1e-9);
         }
-        if let Some(chunk) = self.chunks[chunk_idx].take() {
-            let old_total = chunk.total_size;
-            let estimated_total = self.estimated_chunk_total(chunk_idx);
-            let diff = estimated_total - old_total;
-            self.update_cumulative_sizes(chunk_idx, diff)
-                .map_err(|e| convert_error("CumulativeUpdateError", &e))?;
-            self.chunk_access.remove(&chunk_idx);
+        // Loading another chunk evicts within the cap
+        assert!(!ok(capped.is_item_measured(55)));
+        ok(capped.update_item_size(55, 4.0));
+        assert_eq!(capped.chunks.len(), 3);
+        assert!(capped.verify_integrity().is_ok());
+
+        let mut config = VirtualListConfig::new();
+        config.set_max_loaded_chunks(None);
+        let mut uncapped = ok(VirtualList::with_sizes(
+            &sizes,
+            10,
+            Orientation::Vertical,
+            config,
+        ));
+        assert_eq!(uncapped.chunks.len(), 10);
+        assert_eq!(uncapped.measured_indices().len(), 100);
+        let positions: Vec<f64> = sizes
+            .iter()
+            .scan(0.0, |sum, &size| {
+                let position = *sum;
+                *sum += size;
+                Some(position)
+            })
+            .collect();
+        assert_eq!(self::positions(&mut uncapped), positions);
+    }
+
+    #[test]
+    fn generation_bumps_once_per_layout_change() {
+        let mut config = VirtualListConfig::new();
+        config.set_max_loaded_chunks(Some(3));
+        let mut list = list_with(100, 10, 10.0, config.clone());
+        let generation = list.generation();
+        ok(list.set_total_items(100));
+        ok(list.append_items(0));
+        assert_eq!(list.generation(), generation);
+        list.freeze();
+        assert_eq!(
+            error_kind(list.set_total_items(100)),
+            VirtualListErrorKind::Frozen
+        );
+        list.unfreeze();
+
+        ok(list.update_item_size_relative(5, 3.0));
+        assert_eq!(list.generation(), generation + 1);
+        ok(list.update_item_sizes(&[15, 25], &[20.0, 20.0]));
+        assert_eq!(list.generation(), generation + 2);
+        // Measuring chunk 3 evicts chunk 0 within the same change
+        ok(list.update_item_size(35, 20.0));
+        assert_eq!(list.generation(), generation + 3);
+        assert_eq!(ok(list.evict_older_than(1)), 2);
+        assert_eq!(list.generation(), generation + 4);
+        ok(list.update_item_sizes(&[55], &[20.0]));
+        config.set_max_loaded_chunks(Some(1));
+        ok(list.set_config(config));
+        assert_eq!(list.chunks.keys().copied().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(list.generation(), generation + 6);
+
+        // A query that evicts a measured chunk counts as a change of its own
+        assert_eq!(list.get_position(60), 610.0);
+        let generation = list.generation();
+        assert_eq!(list.get_position(95), 950.0);
+        assert_eq!(list.chunks.keys().copied().collect::<Vec<_>>(), vec![9]);
+        assert_eq!(list.generation(), generation + 1);
+    }
+
+    #[test]
+    fn skip_zero_sized_start_trims_raw_edges_before_padding() {
+        let mut config = VirtualListConfig::new();
+        config.set_skip_zero_sized_start(true);
+        let mut list = list_with(100, 10, 10.0, config);
+        let mut plain = list_with(100, 10, 10.0, VirtualListConfig::new());
+        for list in [&mut list, &mut plain] {
+            ok(list.update_item_sizes(&[27, 28], &[0.0, 0.0]));
         }
-        Ok(())
+        // The viewport ends exactly on the collapsed items 27 and 28
+        let range = ok(plain.get_visible_range_exact(170.0, 100.0));
+        assert_eq!((range.start, range.end), (17, 29));
+        let range = ok(list.get_visible_range_exact(170.0, 100.0));
+        assert_eq!((range.start, range.end), (17, 27));
+        assert_eq!(range.end_offset, 270.0);
+        assert_eq!(range.last_fully_visible, 26);
+        // Padding is added around the raw edges, whose trailing item (29)
+        // has a size, so a collapsed item inside the padding is kept
+        let padded = ok(plain.get_visible_range(170.0, 100.0));
+        let range = ok(list.get_visible_range(170.0, 100.0));
+        assert_eq!((range.start, range.end), (padded.start, padded.end));
+        assert_eq!(range.end, 29 + 8 + 1);
+
+        let mut config = VirtualListConfig::new();
+        config.set_skip_zero_sized_start(true);
+        let mut short = list_with(3, 10, 10.0, config);
+        ok(short.update_item_size(0, 0.0));
+        let range = ok(short.get_visible_range(0.0, 10000.0));
+        assert_eq!((range.start, range.end), (1, 3));
+        assert_eq!(range.start_offset, 0.0);
     }
 
-    fn estimated_chunk_total(&self, chunk_idx: usize) -> f64 {
-        let items_in_chunk =
-            if chunk_idx == self.chunks.len() - 1 && self.total_items % self.chunk_size != 0 {
-                self.total_items % self.chunk_size
-            } else {
-                self.chunk_size
-            };
-        items_in_chunk as f64 * self.estimated_size
+    #[test]
+    fn reserve_measurements_preallocates_chunk_bookkeeping() {
+        let mut config = VirtualListConfig::new();
+        config.set_max_loaded_chunks(None);
+        let mut list = list_with(10_000, 10, 10.0, config);
+        let generation = list.generation();
+        list.reserve_measurements(2_000);
+        let capacity = list.chunk_access.capacity();
+        assert!(capacity >= 201);
+        assert_eq!(list.generation(), generation);
+        let indices: Vec<u32> = (0..2_000).collect();
+        ok(list.update_item_sizes(&indices, &vec![12.0; 2_000]));
+        assert_eq!(list.chunk_access.len(), 200);
+        assert_eq!(list.chunk_access.capacity(), capacity);
+
+        // Bounded by the chunk count and the loaded-chunk cap
+        let mut small = list_with(30, 10, 10.0, VirtualListConfig::new());
+        small.reserve_measurements(usize::MAX);
+        assert!(small.chunk_access.capacity() >= 3);
+        let mut capped = list_with(10_000, 10, 10.0, VirtualListConfig::new());
+        capped.reserve_measurements(usize::MAX);
+        assert!(capped.chunk_access.capacity() < 1_000);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn construction_does_no_per_chunk_work() {
+        let total_items = 1 << 40;
+        let mut list = list(total_items, 100, 20.0);
+        assert!(list.chunks.is_empty());
+        assert!(list.chunk_offsets.tree.is_empty());
+        assert_eq!(list.total_size(), total_items as f64 * 20.0);
+        assert_eq!(
+            list.get_position(total_items / 2),
+            (total_items / 2) as f64 * 20.0
+        );
+        let range = ok(list.get_visible_range((total_items / 2) as f64 * 20.0, 200.0));
+        assert!(range.start <= total_items / 2 && range.end > total_items / 2 + 9);
+        assert!(list.chunks.len() <= 3);
+        ok(list.unload_chunk(total_items / 200));
+        assert_eq!(list.total_size(), total_items as f64 * 20.0);
+    }
+
+    #[test]
+    fn sparse_chunks_keep_measurements_across_resizes() {
+        let mut list = list(95, 10, 10.0);
+        ok(list.update_item_sizes(&[4, 57, 93], &[30.0, 0.0, 25.0]));
+        assert_eq!(
+            list.chunks.keys().copied().collect::<Vec<_>>(),
+            vec![0, 5, 9]
+        );
+        ok(list.set_total_items(60));
+        assert_eq!(list.chunks.keys().copied().collect::<Vec<_>>(), vec![0, 5]);
+        assert!(list.verify_integrity().is_ok());
+        assert_eq!(list.total_size(), 600.0 + 20.0 - 10.0);
+        ok(list.set_chunk_size(7));
+        assert!(list.verify_integrity().is_ok());
+        assert_eq!(list.get_position(58), 580.0 + 20.0 - 10.0);
     }
 }